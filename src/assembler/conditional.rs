@@ -0,0 +1,119 @@
+use super::diagnostics::AssembleError;
+use super::expr::{self, ValueError};
+use super::token::Token;
+
+// name, value (from `!define NAME value` or `-D NAME=value`)
+pub type Define = (String, String);
+
+// the open `!if`/`!ifdef`/`!ifndef` blocks of one file, innermost last
+// blocks can't be opened in one file and closed in another
+#[derive(Debug, Default)]
+pub struct Conditions {
+    // the directive that opened it, whether the lines around it are kept, whether its condition held, and if `!else` was seen
+    blocks: Vec<(Token, bool, bool, bool)>,
+}
+
+impl Conditions {
+    // whether lines here are kept
+    pub fn active(&self) -> bool {
+        self.blocks.last().is_none_or(|(_, outer, condition, in_else)| *outer && condition != in_else)
+    }
+
+    // handles the line if it's one of the conditional directives (or a `!define`), returning whether it was
+    pub fn directive(&mut self, line: &[Token], defines: &mut Vec<Define>, errors: &mut Vec<AssembleError>) -> bool {
+        let active = self.active();
+        match line[0].text.as_str() {
+            "!define" => {
+                if !active { return true; }
+                let Some(name) = line.get(1) else {
+                    errors.push(AssembleError::after_line(line, String::from("expected a name after `!define`")));
+                    return true;
+                };
+                // a define without a value is just switched on
+                let value = line.get(2).map_or("1", |token| token.text.as_str());
+                defines.retain(|(define, _)| *define != name.text);
+                defines.push((name.text.clone(), value.to_string()));
+            },
+            "!ifdef" | "!ifndef" => {
+                let condition = match line.get(1) {
+                    Some(name) => defines.iter().any(|(define, _)| *define == name.text) == (line[0] == "!ifdef"),
+                    None => {
+                        if active { errors.push(AssembleError::after_line(line, format!("expected a name after `{}`", line[0]))); }
+                        false
+                    },
+                };
+                self.blocks.push((line[0].clone(), active, condition, false));
+            },
+            "!if" => {
+                // a block inside one that's left out isn't evaluated, so it can use names that aren't defined
+                let condition = active && evaluate(line, defines, errors);
+                self.blocks.push((line[0].clone(), active, condition, false));
+            },
+            "!else" => match self.blocks.last_mut() {
+                Some((_, _, _, in_else)) if !*in_else => *in_else = true,
+                Some(_) => errors.push(AssembleError::at_token(&line[0], String::from("this block already had an `!else`"))),
+                None => errors.push(AssembleError::at_token(&line[0], String::from("`!else` without an `!if` before it"))),
+            },
+            "!endif" => if self.blocks.pop().is_none() {
+                errors.push(AssembleError::at_token(&line[0], String::from("`!endif` without an `!if` before it")));
+            },
+            _ => return false,
+        } true
+    }
+
+    // errors for the blocks left open at the end of the file
+    pub fn finish(self, errors: &mut Vec<AssembleError>) {
+        for (directive, ..) in self.blocks {
+            errors.push(AssembleError::at_token(&directive, format!("`{}` is never closed with `!endif`", directive)));
+        }
+    }
+}
+
+// the condition of an `!if`, where defines stand for their values (which have to be numbers)
+fn evaluate(line: &[Token], defines: &[Define], errors: &mut Vec<AssembleError>) -> bool {
+    let expression = line[1..].iter().map(|token| token.text.as_str()).collect::<Vec<&str>>();
+    if expression.is_empty() {
+        errors.push(AssembleError::after_line(line, String::from("expected a condition after `!if`")));
+        return false;
+    }
+    let lookup = |name: &str| match defines.iter().rev().find(|(define, _)| define == name) {
+        Some((_, value)) => expr::evaluate(value, &|name| Err(ValueError::Undefined(name.to_string())))
+            .map_err(|_| ValueError::Other(format!("`{}` is defined as `{}`, which isn't a number", name, value))),
+        None => Err(ValueError::Undefined(name.to_string())),
+    };
+    match expr::evaluate(&expression.join(" "), &lookup) {
+        Ok(value) => value != 0,
+        Err(message) => {
+            errors.push(AssembleError::at_token(&line[0], message));
+            false
+        },
+    }
+}
+
+// replaces every token that's the name of a define with its value
+pub fn substitute(line: Vec<Token>, defines: &[Define]) -> Vec<Token> {
+    line.into_iter().map(|token| match defines.iter().rev().find(|(name, _)| token == *name) {
+        Some((_, value)) => token.with_text(value.clone()),
+        None => token,
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::{assemble_with, AssembleOptions};
+
+    // the error messages from assembling the source with the given -D defines
+    fn errors(source: &str, defines: &[(&str, &str)]) -> Vec<String> {
+        let options = AssembleOptions { defines: defines.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(), ..AssembleOptions::default() };
+        assemble_with("test.mca", source, &options).err().unwrap_or_default().into_iter().map(|error| error.message).collect()
+    }
+
+    #[test]
+    fn defines_in_conditions_have_to_be_numbers() {
+        assert!(errors("!if SPEED > 2\nKill\n!endif", &[("SPEED", "4")]).is_empty());
+        assert_eq!(errors("!if SPEED > 2\nKill\n!endif", &[("SPEED", "fast")]), ["`SPEED` is defined as `fast`, which isn't a number"]);
+        assert_eq!(errors("!define MODE fast\n!if MODE\n!endif", &[]), ["`MODE` is defined as `fast`, which isn't a number"]);
+        assert_eq!(errors("!if MISSING\n!endif", &[]), ["`MISSING` isn't defined"]);
+        assert!(errors("!define MODE fast\n!if defined(MODE) && !defined(OTHER)\nKill\n!endif", &[]).is_empty());
+    }
+}
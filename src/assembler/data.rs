@@ -0,0 +1,140 @@
+use super::diagnostics::AssembleError;
+use super::expr;
+use super::include::relative_path;
+use super::token::{Span, Token};
+
+// the directives that place raw words in a page instead of instructions
+pub static DIRECTIVES: &[&str] = &["!byte", "!word", "!fill", "!string", "!incbin"];
+
+// the directives that set what ram and disc start out holding
+pub static MEMORY_DIRECTIVES: &[&str] = &["!ram", "!disc"];
+
+// the most words a single `!fill` can place (every page of the program)
+const MAX_FILL: i64 = 256 * 256;
+
+// splits every data directive into a line for each word it places, which is either `!byte value` or `!word value`,
+// so headers and page splitting can count them like instructions
+//     !byte 1 2 SCALE-1     a word for each value, which has to fit in 8 bits
+//     !word 0x12345678      a word for each value, taken as is (32 bits)
+//     !fill 16 0xFF         the value (0 if it's left out) that many times
+//     !string "hi\n"        a word for each character
+//     !incbin "table.bin"   a word for each byte of the file, relative to the file it's written in
+// count works out the count of a `!fill`, which can use constants
+pub fn expand_data(script: &mut Vec<Vec<Token>>, count: &dyn Fn(&str) -> Result<i64, String>, errors: &mut Vec<AssembleError>) {
+    let mut line_index = 0;
+    while line_index < script.len() {
+        let line = &script[line_index];
+        if !DIRECTIVES.iter().any(|directive| line[0] == *directive) {
+            line_index += 1;
+            continue;
+        }
+        let words = data_words(line, count, errors);
+        let placed = words.len();
+        script.splice(line_index..=line_index, words);
+        line_index += placed;
+    }
+}
+
+fn data_words(line: &[Token], count: &dyn Fn(&str) -> Result<i64, String>, errors: &mut Vec<AssembleError>) -> Vec<Vec<Token>> {
+    let directive = &line[0];
+    let byte = |value: &Token| vec![directive.with_text(String::from("!byte")), value.clone()];
+    if line.len() == 1 {
+        errors.push(AssembleError::after_line(line, format!("expected a value after `{}`", directive)));
+        return vec![];
+    }
+    match directive.text.as_str() {
+        "!byte" => line[1..].iter().map(byte).collect(),
+        "!word" => line[1..].iter().map(|value| vec![directive.with_text(String::from("!word")), value.clone()]).collect(),
+        "!fill" => {
+            let value = line.get(2).cloned().unwrap_or_else(|| line[1].with_text(String::from("0")));
+            if let Some(extra) = line.get(3) {
+                errors.push(AssembleError::at_token(extra, String::from("`!fill` takes a count and a value")));
+            }
+            match count(&line[1].text) {
+                Ok(count) if (0..=MAX_FILL).contains(&count) => vec![byte(&value); count as usize],
+                Ok(count) => {
+                    errors.push(AssembleError::at_token(&line[1], format!("can't fill {} words (0 to {})", count, MAX_FILL)));
+                    vec![]
+                },
+                Err(message) => {
+                    errors.push(AssembleError::at_token(&line[1], message));
+                    vec![]
+                },
+            }
+        },
+        "!string" => line[1..].iter().flat_map(|string| match string_bytes(&string.text) {
+            Ok(bytes) => bytes.into_iter().map(|value| byte(&string.with_text(value.to_string()))).collect(),
+            Err(message) => {
+                errors.push(AssembleError::at_token(string, message));
+                vec![]
+            },
+        }).collect(),
+        _ => {
+            let path = relative_path(&line[1].span.file, &line[1].text);
+            match std::fs::read(&path) {
+                Ok(bytes) => bytes.into_iter().map(|value| byte(&line[1].with_text(value.to_string()))).collect(),
+                Err(err) => {
+                    errors.push(AssembleError::at_token(&line[1], format!("couldn't read `{}`: {}", path, err)));
+                    vec![]
+                },
+            }
+        },
+    }
+}
+
+// the starting contents of ram (directive `!ram`) or disc (`!disc`), from every `!ram address values...` line of the pages
+// values are bytes or "strings", and value works out one of them (or the address) on the given page
+// empty if nothing is set, so the program only has an image when it asks for one
+pub fn memory_image(directive: &str, pages: &[(Vec<Vec<Token>>, String)], value: &dyn Fn(&str, usize) -> Result<u8, String>,
+        errors: &mut Vec<AssembleError>) -> Vec<u8> {
+    let memory = &directive[1..];
+    let mut image = vec![];
+    let mut set_by: Vec<Option<Span>> = vec![None; 256];
+    for (page, (script, _)) in pages.iter().enumerate() {
+        for line in script.iter().filter(|line| line[0] == directive) {
+            if line.len() < 3 {
+                errors.push(AssembleError::after_line(line, format!("expected an address and values after `{}`", directive)));
+                continue;
+            }
+            let address = match value(&line[1].text, page) {
+                Ok(address) => address as usize,
+                Err(message) => { errors.push(AssembleError::at_token(&line[1], message)); continue; },
+            };
+            let mut bytes = vec![];
+            for token in &line[2..] {
+                let token_bytes = if token.text.starts_with('"') { string_bytes(&token.text) } else { value(&token.text, page).map(|byte| vec![byte]) };
+                match token_bytes {
+                    Ok(token_bytes) => bytes.extend(token_bytes),
+                    Err(message) => errors.push(AssembleError::at_token(token, message)),
+                }
+            }
+            if address + bytes.len() > 256 {
+                errors.push(AssembleError::at_token(&line[1], format!("{} bytes starting at {} run past the end of {} (256 bytes)", bytes.len(), address, memory)));
+                continue;
+            }
+            if image.is_empty() { image = vec![0; 256]; }
+            for (offset, byte) in bytes.into_iter().enumerate() {
+                if let Some(span) = &set_by[address + offset] {
+                    errors.push(AssembleError::at_token(&line[0], format!("{} address {} was already set at {}", memory, address + offset, span)));
+                    break;
+                }
+                set_by[address + offset] = Some(line[0].span.clone());
+                image[address + offset] = byte;
+            }
+        }
+    } image
+}
+
+// the characters of a "quoted string", with its escapes worked out
+fn string_bytes(text: &str) -> Result<Vec<u8>, String> {
+    let Some(inner) = text.strip_prefix('"').and_then(|text| text.strip_suffix('"')).filter(|_| text.len() >= 2) else {
+        return Err(format!("expected a \"quoted string\", found `{}`", text));
+    };
+    let mut bytes = vec![];
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = if c == '\\' { chars.next().and_then(expr::escape).ok_or_else(expr::escape_error)? } else { c };
+        bytes.push(u8::try_from(c as u32).map_err(|_| format!("`{}` doesn't fit in a byte", c))?);
+    }
+    Ok(bytes)
+}
@@ -0,0 +1,95 @@
+use super::token::{Span, Token};
+
+// an error found while assembling, pointing at the source that caused it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    pub file: String,
+    pub line: usize,    // 1 based
+    pub column: usize,  // 1 based
+    pub length: usize,  // how many characters to underline
+    pub message: String,
+    pub source_line: String,  // the original line, filled in once the whole file has been looked at
+    // extra context printed under the source, like which macro calls the error came through
+    pub notes: Vec<String>,
+    pub warning: bool,  // warnings are reported the same way, but don't stop the program from assembling
+}
+
+impl AssembleError {
+    pub fn new(span: &Span, message: String) -> AssembleError {
+        AssembleError {
+            file: span.file.to_string(),
+            line: span.line,
+            column: span.column,
+            length: span.length.max(1),
+            message,
+            source_line: String::new(),
+            notes: vec![],
+            warning: false,
+        }
+    }
+
+    pub fn at_token(token: &Token, message: String) -> AssembleError {
+        let mut err = AssembleError::new(&token.span, message);
+        err.notes = token.expansions.iter().map(|expansion| expansion.to_string()).collect();
+        err
+    }
+
+    // points just past the end of a line (for when something is missing)
+    pub fn after_line(line: &[Token], message: String) -> AssembleError {
+        let last = line.last().expect("lines are never empty");
+        let mut err = AssembleError::at_token(last, message);
+        err.column += err.length;
+        err.length = 1;
+        err
+    }
+
+    pub fn as_warning(mut self) -> AssembleError {
+        self.warning = true;
+        self
+    }
+
+    // fills in the source line from whichever of the (file, source) pairs the error is in
+    pub fn with_source(mut self, sources: &[(String, String)]) -> AssembleError {
+        if let Some((_, source)) = sources.iter().find(|(file, _)| *file == self.file) {
+            self.source_line = source.lines().nth(self.line.saturating_sub(1)).unwrap_or_default().to_string();
+        } self
+    }
+}
+
+impl std::fmt::Display for AssembleError {
+    // rendered similar to rustc:
+    // error: <message>
+    //  --> file:line:column
+    //   |
+    // 3 |     Ldi rdx 5
+    //   |         ^^^
+    //   = note: in macro `name` called from file:line
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        writeln!(f, "{}: {}", if self.warning { "warning" } else { "error" }, self.message)?;
+        writeln!(f, "{}--> {}:{}:{}", gutter, self.file, self.line, self.column)?;
+        writeln!(f, "{} |", gutter)?;
+        writeln!(f, "{} | {}", line_number, self.source_line)?;
+        // keeping tabs so the underline lines up with the source
+        let padding = self.source_line.chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        write!(f, "{} | {}{}", gutter, padding, "^".repeat(self.length))?;
+        for note in &self.notes {
+            write!(f, "\n{} = note: {}", gutter, note)?;
+        } Ok(())
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+// renders every error, followed by a summary line
+pub fn render_errors(errors: &[AssembleError]) -> String {
+    let mut text = errors.iter().map(|err| format!("{}\n\n", err)).collect::<String>();
+    match errors.len() {
+        1 => text.push_str("error: aborting due to 1 previous error"),
+        count => text.push_str(&format!("error: aborting due to {} previous errors", count)),
+    } text
+}
@@ -0,0 +1,276 @@
+// a small C-like expression evaluator, for `!if` conditions and operands
+// numbers can be decimal, 0x hex, 0b binary, or 'c' characters, and names are looked up by the caller (which gives the error
+// for names it doesn't know)
+// operators, loosest first: ||, &&, |, ^, &, == !=, < > <= >=, << >>, + -, * / %, then unary - ! ~
+// functions: lo(x) and hi(x) for the low and high byte of a 16 bit value, and defined(NAME)
+pub fn evaluate(text: &str, lookup: &dyn Fn(&str) -> Result<i64, ValueError>) -> Result<i64, String> {
+    let mut parser = Parser { chars: text.chars().collect(), position: 0, lookup };
+    let value = parser.binary(0)?;
+    parser.skip_spaces();
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected `{}` in `{}`", c, text)),
+    }
+}
+
+// why a name has no value, so a name that isn't anything at all can be told apart from one that's broken
+pub enum ValueError {
+    Undefined(String),
+    Other(String),
+}
+
+impl std::fmt::Display for ValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ValueError::Undefined(name) => write!(f, "`{}` isn't defined", name),
+            ValueError::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+// the binary operators, grouped from loosest to tightest binding
+static PRECEDENCE: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<=", ">=", "<", ">"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+struct Parser<'a> {
+    chars: Vec<char>,
+    position: usize,
+    lookup: &'a dyn Fn(&str) -> Result<i64, ValueError>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) { self.position += 1; }
+    }
+
+    // takes the operator if it's next (without mistaking || for | or << for <)
+    fn take_operator(&mut self, operator: &str) -> bool {
+        self.skip_spaces();
+        let length = operator.chars().count();
+        let matches = self.chars.get(self.position..self.position + length).is_some_and(|chars| chars.iter().copied().eq(operator.chars()));
+        let doubled = length == 1 && self.chars.get(self.position + 1).is_some_and(|next| *next == operator.chars().next().unwrap() && "|&<>".contains(*next));
+        let followed_by_equals = length == 1 && "<>!=".contains(operator) && self.chars.get(self.position + 1) == Some(&'=');
+        if matches && !doubled && !followed_by_equals {
+            self.position += length;
+            true
+        } else { false }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<i64, String> {
+        if level == PRECEDENCE.len() { return self.unary(); }
+        let mut left = self.binary(level + 1)?;
+        'operators: loop {
+            for operator in PRECEDENCE[level] {
+                if self.take_operator(operator) {
+                    let right = self.binary(level + 1)?;
+                    left = apply(operator, left, right)?;
+                    continue 'operators;
+                }
+            }
+            return Ok(left);
+        }
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some('-') => { self.position += 1; Ok(self.unary()?.wrapping_neg()) },
+            Some('!') => { self.position += 1; Ok((self.unary()? == 0) as i64) },
+            Some('~') => { self.position += 1; Ok(!self.unary()?) },
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<i64, String> {
+        self.skip_spaces();
+        match self.peek() {
+            None => Err(String::from("expected a value, found the end of the expression")),
+            Some('(') => {
+                self.position += 1;
+                let value = self.binary(0)?;
+                self.expect(')')?;
+                Ok(value)
+            },
+            Some('\'') => self.character(),
+            Some(c) if c.is_ascii_digit() => self.number(),
+            Some(c) if is_name_char(c) => {
+                let name = self.name();
+                self.skip_spaces();
+                if self.peek() != Some('(') {
+                    return (self.lookup)(&name).map_err(|error| error.to_string());
+                }
+                self.position += 1;
+                let value = match name.as_str() {
+                    "defined" => {
+                        self.skip_spaces();
+                        let name = self.name();
+                        // a name with a value that's broken is still defined
+                        !matches!((self.lookup)(&name), Err(ValueError::Undefined(_))) as i64
+                    },
+                    "lo" => self.binary(0)? & 0xFF,
+                    "hi" => (self.binary(0)? >> 8) & 0xFF,
+                    _ => return Err(format!("unknown function `{}` (expected lo, hi or defined)", name)),
+                };
+                self.expect(')')?;
+                Ok(value)
+            },
+            Some(c) => Err(format!("expected a value, found `{}`", c)),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_spaces();
+        match self.peek() {
+            Some(c) if c == expected => { self.position += 1; Ok(()) },
+            Some(c) => Err(format!("expected `{}`, found `{}`", expected, c)),
+            None => Err(format!("expected `{}`, found the end of the expression", expected)),
+        }
+    }
+
+    fn name(&mut self) -> String {
+        let start = self.position;
+        while self.peek().is_some_and(is_name_char) { self.position += 1; }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn number(&mut self) -> Result<i64, String> {
+        let text = self.name();
+        let (digits, radix) = match text.get(..2) {
+            Some("0x" | "0X") => (&text[2..], 16),
+            Some("0b" | "0B") => (&text[2..], 2),
+            _ => (text.as_str(), 10),
+        };
+        i64::from_str_radix(&digits.replace('_', ""), radix).map_err(|_| format!("`{}` isn't a valid number", text))
+    }
+
+    fn character(&mut self) -> Result<i64, String> {
+        self.position += 1;
+        let value = match self.peek() {
+            Some('\\') => {
+                self.position += 1;
+                self.peek().and_then(escape).ok_or_else(escape_error)?
+            },
+            Some(c) => c,
+            None => return Err(String::from("unclosed character literal")),
+        };
+        self.position += 1;
+        if self.peek() != Some('\'') { return Err(String::from("a character literal holds exactly one character")); }
+        self.position += 1;
+        Ok(value as i64)
+    }
+}
+
+// the character a backslash and c stand for (\n, \t, \r, \0, \\, \' and \"), in character literals and strings
+pub fn escape(c: char) -> Option<char> {
+    match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        '\\' | '\'' | '"' => Some(c),
+        _ => None,
+    }
+}
+
+pub fn escape_error() -> String {
+    String::from("unknown escape (expected \\n, \\t, \\r, \\0, \\\\, \\' or \\\")")
+}
+
+// names can hold most symbols (like header names can), just not operators or brackets
+pub fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || "_~.$@:?#".contains(c)
+}
+
+fn apply(operator: &str, left: i64, right: i64) -> Result<i64, String> {
+    Ok(match operator {
+        "||" => (left != 0 || right != 0) as i64,
+        "&&" => (left != 0 && right != 0) as i64,
+        "|" => left | right,
+        "^" => left ^ right,
+        "&" => left & right,
+        "==" => (left == right) as i64,
+        "!=" => (left != right) as i64,
+        "<" => (left < right) as i64,
+        ">" => (left > right) as i64,
+        "<=" => (left <= right) as i64,
+        ">=" => (left >= right) as i64,
+        "<<" => left.checked_shl(right as u32).ok_or_else(|| format!("can't shift by {}", right))?,
+        ">>" => left.checked_shr(right as u32).ok_or_else(|| format!("can't shift by {}", right))?,
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "*" => left.wrapping_mul(right),
+        "/" => left.checked_div(right).ok_or("division by zero")?,
+        _ => left.checked_rem(right).ok_or("division by zero")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str) -> Result<i64, String> {
+        evaluate(text, &|name| match name {
+            "SCALE" => Ok(16),
+            _ => Err(ValueError::Undefined(name.to_string())),
+        })
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(7));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(9));
+        assert_eq!(eval("1 << 2 + 1"), Ok(8));
+        assert_eq!(eval("1 | 4 ^ 3 & 6"), Ok(7));
+        assert_eq!(eval("1 < 2 == 1"), Ok(1));
+        assert_eq!(eval("0 || 1 && 0"), Ok(0));
+        assert_eq!(eval("10 - 4 - 3"), Ok(3));
+        assert_eq!(eval("-2 * -3"), Ok(6));
+        assert_eq!(eval("!0 + ~0"), Ok(0));
+    }
+
+    #[test]
+    fn operators_that_share_a_prefix() {
+        assert_eq!(eval("1 <= 1"), Ok(1));
+        assert_eq!(eval("2 >= 3"), Ok(0));
+        assert_eq!(eval("3 != 3"), Ok(0));
+        assert_eq!(eval("6 & 3 && 1"), Ok(1));
+        assert_eq!(eval("4 >> 1 > 1"), Ok(1));
+    }
+
+    #[test]
+    fn values() {
+        assert_eq!(eval("0x1F + 0b101 + 1_000"), Ok(31 + 5 + 1000));
+        assert_eq!(eval("'a' + '\\n'"), Ok(97 + 10));
+        assert_eq!(eval("SCALE - 1"), Ok(15));
+        assert_eq!(eval("lo(0x1234) + hi(0x1234)"), Ok(0x34 + 0x12));
+        assert_eq!(eval("defined(SCALE) + defined(OTHER)"), Ok(1));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval("1 +"), Err(String::from("expected a value, found the end of the expression")));
+        assert_eq!(eval("(1 + 2"), Err(String::from("expected `)`, found the end of the expression")));
+        assert_eq!(eval("1 2"), Err(String::from("unexpected `2` in `1 2`")));
+        assert_eq!(eval("1 / 0"), Err(String::from("division by zero")));
+        assert_eq!(eval("1 % 0"), Err(String::from("division by zero")));
+        assert_eq!(eval("0xZZ"), Err(String::from("`0xZZ` isn't a valid number")));
+        assert_eq!(eval("'ab'"), Err(String::from("a character literal holds exactly one character")));
+        assert_eq!(eval("'\\q'"), Err(escape_error()));
+        assert_eq!(eval("sqrt(4)"), Err(String::from("unknown function `sqrt` (expected lo, hi or defined)")));
+        assert_eq!(eval("WIDTH * 2"), Err(String::from("`WIDTH` isn't defined")));
+    }
+}
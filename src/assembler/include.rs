@@ -0,0 +1,123 @@
+use std::path::{Component, Path, PathBuf};
+
+use super::conditional::{self, Conditions, Define};
+use super::diagnostics::AssembleError;
+use super::prelude;
+use super::token::{tokenize, Token};
+
+// tokenizes a file, splicing in the lines of every `!include "path"` (each time), `!import "path"` (only the first time)
+// and `!use library` (a built-in library, also only the first time)
+// paths are relative to the file they're written in, and every file read gets added to sources
+// conditional blocks are handled along the way, so an include in a block that's left out is never read
+pub fn load(file: &str, source: &str, defines: Vec<Define>, sources: &mut Vec<(String, String)>, errors: &mut Vec<AssembleError>) -> Vec<Vec<Token>> {
+    sources.push((file.to_string(), source.to_string()));
+    let mut loader = Loader { chain: vec![file.to_string()], imported: vec![canonical(file)], defines, sources, errors };
+    loader.load_file(file, source)
+}
+
+struct Loader<'a> {
+    chain: Vec<String>,  // every file currently being included, from the top one down, for catching files that include themselves
+    imported: Vec<PathBuf>,
+    defines: Vec<Define>,
+    sources: &'a mut Vec<(String, String)>,
+    errors: &'a mut Vec<AssembleError>,
+}
+
+impl Loader<'_> {
+    fn load_file(&mut self, file: &str, source: &str) -> Vec<Vec<Token>> {
+        let mut lines = vec![];
+        let mut conditions = Conditions::default();
+        for line in tokenize(file, source, self.errors) {
+            if conditions.directive(&line, &mut self.defines, self.errors) || !conditions.active() { continue; }
+            let line = conditional::substitute(line, &self.defines);
+            if line[0] == "!use" {
+                if let Some((file, source)) = self.use_prelude(&line) {
+                    lines.extend(self.load_file(&file, source));
+                }
+                continue;
+            }
+            if line[0] != "!include" && line[0] != "!import" {
+                lines.push(line);
+                continue;
+            }
+            let Some(path_token) = line.get(1) else {
+                self.errors.push(AssembleError::after_line(&line, format!("expected a path after `{}`", line[0])));
+                continue;
+            };
+            let path = relative_path(file, &path_token.text);
+            let key = canonical(&path);
+            if line[0] == "!import" && self.imported.contains(&key) { continue; }
+            if let Some(start) = self.chain.iter().position(|including| canonical(including) == key) {
+                let cycle = self.chain[start..].iter().chain(std::iter::once(&path)).map(|file| file.as_str()).collect::<Vec<&str>>();
+                self.errors.push(AssembleError::at_token(path_token, format!("`{}` ends up including itself ({})", path, cycle.join(" -> "))));
+                continue;
+            }
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(err) => {
+                    self.errors.push(AssembleError::at_token(path_token, format!("couldn't read `{}`: {}", path, err)));
+                    continue;
+                },
+            };
+            self.imported.push(key);
+            if !self.sources.iter().any(|(source_file, _)| *source_file == path) { self.sources.push((path.clone(), text.clone())); }
+            self.chain.push(path.clone());
+            lines.extend(self.load_file(&path, &text));
+            self.chain.pop();
+        }
+        conditions.finish(self.errors);
+        lines
+    }
+
+    // `!use std` or `!use std 1`, which imports one of the libraries built into the assembler (None if it's already imported)
+    fn use_prelude(&mut self, line: &[Token]) -> Option<(String, &'static str)> {
+        let Some(name) = line.get(1) else {
+            self.errors.push(AssembleError::after_line(line, String::from("expected a library name after `!use`")));
+            return None;
+        };
+        let version = match line.get(2) {
+            Some(token) => match token.text.parse::<usize>() {
+                Ok(version) => Some(version),
+                Err(_) => {
+                    self.errors.push(AssembleError::at_token(token, format!("expected a version number, found `{}`", token)));
+                    return None;
+                },
+            },
+            None => None,
+        };
+        let (file, source) = match prelude::find(&name.text, version) {
+            Ok(prelude) => prelude,
+            Err(message) => {
+                self.errors.push(AssembleError::at_token(name, message));
+                return None;
+            },
+        };
+        if self.imported.contains(&PathBuf::from(&file)) { return None; }
+        self.imported.push(PathBuf::from(&file));
+        self.sources.push((file.clone(), source.to_string()));
+        Some((file, source))
+    }
+}
+
+// a quoted path written in a file, relative to that file
+pub fn relative_path(file: &str, path: &str) -> String {
+    normalize(&Path::new(file).parent().unwrap_or(Path::new("")).join(path.trim_matches('"')))
+}
+
+// the same file can be reached through different relative paths
+fn canonical(file: &str) -> PathBuf {
+    std::fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file))
+}
+
+// drops the `dir/..` pairs so errors and listings show lib/abi.mca rather than tetris/../lib/abi.mca
+fn normalize(path: &Path) -> String {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir if matches!(normalized.components().next_back(), Some(Component::Normal(_))) => { normalized.pop(); },
+            Component::CurDir => {},
+            component => normalized.push(component),
+        }
+    }
+    normalized.to_string_lossy().to_string()
+}
@@ -0,0 +1,106 @@
+use super::diagnostics::AssembleError;
+use super::Program;
+use crate::disasm::decode;
+use crate::isa::PAGE_SIZE;
+
+// what's known about the page register (the page Jmp/Jiz/Jnz land in) when an instruction runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageReg {
+    Known(u8),
+    Unknown,  // set from a register, or reached from places that disagree
+}
+
+// follows every path from the start of the program, tracking what the page register provably holds,
+// and warns about branches to a header on a different page than that
+// a Goto or GotoReg right after a PshCon is a call (see `call` in std), which comes back to the next line once ret
+// sets the page register to the pushed page, so the lines after a call are followed too
+// lines only reached through JmpR/GotoReg otherwise count as unknown, so those never warn
+pub fn check_page_register(program: &Program) -> Vec<AssembleError> {
+    let mut states = program.pages.iter().map(|page| vec![None; page.words.len()]).collect::<Vec<Vec<Option<PageReg>>>>();
+    let mut queue = vec![];
+    merge(&mut states, &mut queue, (0, 0), PageReg::Known(0));
+    while let Some((page, line)) = queue.pop() {
+        let state = states[page][line].unwrap();
+        // running off the end of a bank keeps going into the next one, without touching the page register
+        let next = if line + 1 == PAGE_SIZE { (page + 1, 0) } else { (page, line + 1) };
+        let Some((name, values)) = decode(program.pages[page].words[line]) else {
+            merge(&mut states, &mut queue, next, state);
+            continue;
+        };
+        let branch_target = match state {
+            PageReg::Known(value) => Some((value as usize, values.first().copied().unwrap_or_default() as usize)),
+            PageReg::Unknown => None,
+        };
+        let pushed_page = match line.checked_sub(1).and_then(|previous| decode(program.pages[page].words[previous])) {
+            Some(("PshCon", pushed)) => Some(pushed[0]),
+            _ => None,
+        };
+        if let (Some(pushed), "Goto" | "GotoReg") = (pushed_page, name) {
+            merge(&mut states, &mut queue, next, PageReg::Known(pushed));
+        }
+        match name {
+            "Kill" | "JmpR" | "GotoReg" => {},
+            "SetPage" => merge(&mut states, &mut queue, next, PageReg::Known(values[0])),
+            "SetPageReg" => merge(&mut states, &mut queue, next, PageReg::Unknown),
+            "Goto" => merge(&mut states, &mut queue, (values[1] as usize, values[0] as usize), PageReg::Known(values[1])),
+            "Jmp" => if let Some(target) = branch_target { merge(&mut states, &mut queue, target, state); },
+            "Jiz" | "Jnz" => {
+                if let Some(target) = branch_target { merge(&mut states, &mut queue, target, state); }
+                merge(&mut states, &mut queue, next, state);
+            },
+            _ => merge(&mut states, &mut queue, next, state),
+        }
+    }
+
+    let page_name = |page: usize| program.pages.get(page).map_or_else(|| page.to_string(), |page| page.name.clone());
+    let mut warnings = vec![];
+    for (page_index, page) in program.pages.iter().enumerate() {
+        for (line, target) in page.targets.iter().enumerate() {
+            let (Some(PageReg::Known(value)), Some((header, _, header_page))) = (states[page_index][line], target) else { continue };
+            let mnemonic = &page.origins[line];
+            if value as usize == *header_page || !["Jmp", "Jiz", "Jnz"].iter().any(|op| *mnemonic == *op) { continue; }
+            let mut warning = AssembleError::at_token(mnemonic, format!(
+                "`{}` to `{}` on page `{}`, but the page register holds page `{}` here",
+                mnemonic, header, page_name(*header_page), page_name(value as usize))).as_warning();
+            warning.notes.push(format!("set it with `SetPage {}` first, or use `Goto {}::{}`", page_name(*header_page), page_name(*header_page), header));
+            warnings.push(warning);
+        }
+    } warnings
+}
+
+// combines what's known at a line with a new path reaching it, queueing the line again if that changed anything
+fn merge(states: &mut [Vec<Option<PageReg>>], queue: &mut Vec<(usize, usize)>, (page, line): (usize, usize), state: PageReg) {
+    // jumps past the end of the program are the emulator's problem
+    let Some(slot) = states.get_mut(page).and_then(|lines| lines.get_mut(line)) else { return };
+    let merged = match *slot {
+        None => state,
+        Some(old) if old == state => return,
+        Some(_) => PageReg::Unknown,
+    };
+    if *slot != Some(merged) {
+        *slot = Some(merged);
+        queue.push((page, line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler::{assemble_with, AssembleOptions};
+
+    fn warnings(source: &str) -> Vec<String> {
+        let program = assemble_with("test.mca", source, &AssembleOptions::default()).unwrap();
+        program.warnings.into_iter().map(|warning| warning.message).collect()
+    }
+
+    #[test]
+    fn jump_into_another_page() {
+        assert_eq!(warnings("SetPage other\nJmp main::start\n!header start\nKill\n!page other\nKill"),
+            ["`Jmp` to `start` on page `main`, but the page register holds page `other` here"]);
+    }
+
+    #[test]
+    fn lines_after_a_call_are_checked() {
+        let source = "!use std\n!header start\ncall other::function\nSetPage other\nJmp main::start\n!page other\n!header function\nret";
+        assert_eq!(warnings(source), ["`Jmp` to `start` on page `main`, but the page register holds page `other` here"]);
+    }
+}
@@ -0,0 +1,283 @@
+use super::diagnostics::AssembleError;
+use super::token::{Span, Token};
+use super::{split_qualified, AssembleOptions};
+
+// name, params, body, and where the name was written
+pub type Macro = (String, Vec<Param>, Vec<Vec<Token>>, Span);
+
+// name, default value (`reg=rda`), whether it takes the rest of the arguments (`...rest`),
+// and whether the param after it is its page (`function::page`), which a qualified argument like `Sprites::draw` fills in too
+pub type Param = (String, Option<Token>, bool, bool);
+
+// splices every macro definition out of the page, returning the local ones
+pub fn get_macros(scripts: &mut Vec<Vec<Token>>, global_macros: &mut Vec<Macro>, errors: &mut Vec<AssembleError>) -> Vec<Macro> {
+    let mut macros = vec![];
+    let mut line_number = 0;
+    while line_number < scripts.len() {
+        if scripts[line_number][0] == "!macro" {
+            let start_offset = if scripts[line_number].len() > 1 && scripts[line_number][1] == "-export" { 1 } else { 0 };
+            // finding the ending line
+            let end_line = scripts[line_number + 1..]
+                .iter()
+                .position(|line| line[0] == "!end")
+                .map(|end| end + line_number + 1);
+            // parsing the macro
+            let Some(name) = scripts[line_number].get(1 + start_offset) else {
+                errors.push(AssembleError::after_line(&scripts[line_number], String::from("expected a name for the macro")));
+                scripts.drain(line_number..=end_line.unwrap_or(line_number));
+                continue;
+            };
+            let Some(end_line) = end_line else {
+                // the rest of the page is eaten by the macro, so nothing else gets reported from it
+                errors.push(AssembleError::at_token(&scripts[line_number][0], format!("macro `{}` is never closed with `!end`", name)));
+                scripts.truncate(line_number);
+                break;
+            };
+            let name_token = name.clone();
+            let params = parse_params(&scripts[line_number][2 + start_offset..], errors);
+            let macro_def = (name_token.text.clone(), params, scripts[line_number + 1..end_line].to_vec(), name_token.span.clone());
+            let scope = if start_offset == 1 { &mut *global_macros } else { &mut macros };
+            // only the first definition would ever be expanded, so a second one is almost certainly a mistake
+            if let Some((.., other)) = scope.iter().find(|(other, ..)| *other == name_token.text) {
+                let mut err = AssembleError::at_token(&name_token, format!("macro `{}` is already defined at {}", name_token, other));
+                err.notes.push(String::from("rename one of them, or if it's a file being read twice, use `!import` so it's only read once"));
+                errors.push(err);
+            }
+            else { scope.push(macro_def); }
+            scripts.drain(line_number..=end_line);
+            continue;
+        }
+        line_number += 1;
+    } macros
+}
+
+fn parse_params(tokens: &[Token], errors: &mut Vec<AssembleError>) -> Vec<Param> {
+    let mut params: Vec<Param> = vec![];
+    for token in tokens {
+        let mut param = match (token.text.strip_prefix("..."), token.text.split_once('=')) {
+            (Some(rest), _) => (rest.to_string(), None, true, false),
+            (None, Some((name, default))) => (name.to_string(), Some(token.with_text(default.to_string())), false, false),
+            (None, None) => (token.text.clone(), None, false, false),
+        };
+        if let Some((header, page)) = param.0.split_once("::").filter(|_| param.1.is_none() && !param.2) {
+            if header.is_empty() || page.is_empty() {
+                errors.push(AssembleError::at_token(token, format!("expected a header and page parameter in `{}`", token)));
+            }
+            else if params.last().is_some_and(|last| last.1.is_some()) {
+                errors.push(AssembleError::at_token(token, format!("`{}` needs a default value, since the parameter before it has one", token)));
+            }
+            else {
+                params.push((header.to_string(), None, false, true));
+                params.push((page.to_string(), None, false, false));
+            }
+            continue;
+        }
+        if param.0.is_empty() {
+            errors.push(AssembleError::at_token(token, format!("expected a parameter name in `{}`", token)));
+        } else if params.last().is_some_and(|last| last.2) {
+            errors.push(AssembleError::at_token(token, format!("`...{}` has to be the last parameter", params.last().unwrap().0)));
+        } else if param.1.is_none() && !param.2 && params.last().is_some_and(|last| last.1.is_some()) {
+            errors.push(AssembleError::at_token(token, format!("`{}` needs a default value, since the parameter before it has one", param.0)));
+            // left as its own name, so a call that leaves it out still expands (the program already has an error)
+            param.1 = Some(token.clone());
+        }
+        params.push(param);
+    } params
+}
+
+// the macro's params, like `(reg, value=0, ...rest)`
+fn signature(params: &[Param]) -> String {
+    let params = params.iter().enumerate()
+        .filter(|(index, _)| *index == 0 || !params[index - 1].3)
+        .map(|(index, (name, default, rest, qualified))| match (default, rest) {
+            _ if *qualified => format!("{}::{}", name, params[index + 1].0),
+            (_, true) => format!("...{}", name),
+            (Some(default), _) => format!("{}={}", name, default),
+            (None, _) => name.clone(),
+        }).collect::<Vec<String>>();
+    format!("({})", params.join(", "))
+}
+
+// the tokens each param gets for a call (one for normal params, any number for ...rest), or None if the count is off
+fn match_args(call: &[Token], params: &[Param], errors: &mut Vec<AssembleError>) -> Option<Vec<Vec<Token>>> {
+    // a qualified argument for a `function::page` param is split into the header and its page, as if both were written
+    let mut args = vec![];
+    for arg in &call[1..] {
+        match params.get(args.len()).filter(|param| param.3).and_then(|_| split_qualified(&arg.text)) {
+            Some((page, header)) => args.extend([arg.with_text(header.to_string()), arg.with_text(page.to_string())]),
+            None => args.push(arg.clone()),
+        }
+    }
+    let args = args.iter().collect::<Vec<&Token>>();
+    let fixed = params.iter().filter(|param| !param.2).count();
+    let required = params.iter().filter(|param| !param.2 && param.1.is_none()).count();
+    let variadic = params.iter().any(|param| param.2);
+    if args.len() < required || (!variadic && args.len() > fixed) {
+        // along with the number right before "argument", which decides the plural
+        let (expected, count) = match (variadic, required == fixed) {
+            (true, _) => (format!("at least {}", required), required),
+            (false, true) => (required.to_string(), required),
+            (false, false) => (format!("{} to {}", required, fixed), fixed),
+        };
+        let message = format!("macro `{}` takes {} argument{} {}, found {}",
+            call[0], expected, if count == 1 { "" } else { "s" }, signature(params), args.len());
+        errors.push(if args.len() > fixed { AssembleError::at_token(args[fixed], message) } else { AssembleError::after_line(call, message) });
+        return None;
+    }
+    Some(params.iter().enumerate().map(|(index, (_, default, rest, _))| {
+        if *rest { return args[index.min(args.len())..].iter().map(|arg| (*arg).clone()).collect(); }
+        match args.get(index) {
+            Some(arg) => vec![(*arg).clone()],
+            None => vec![default.as_ref().unwrap().expanded_by(&call[0].text, &call[0])],
+        }
+    }).collect())
+}
+
+// how much has been expanded so far, across every page
+#[derive(Debug, Clone, Default)]
+pub struct Expansions {
+    pub count: usize,  // also what keeps local labels unique
+    pub lines: usize,  // every line spliced in, for stopping runaway expansion
+}
+
+pub fn expand_macro_calls(lines: &mut Vec<Vec<Token>>, macros: &[Macro], expansions: &mut Expansions, options: &AssembleOptions, errors: &mut Vec<AssembleError>) {
+    let mut line_number = 0;
+    while line_number < lines.len() {
+        if macros.iter().any(|(m,..)| lines[line_number][0] == *m) {
+            let starting_line = line_number;
+            let mac = lines.remove(line_number);
+            let (name, params, body, _) = macros.iter().find(|(m,..)| mac[0] == *m).unwrap();
+            // the macros this call is nested in, outermost first
+            let chain = mac[0].expansions.iter().rev().map(|expansion| expansion.name.as_str()).collect::<Vec<&str>>();
+            if chain.contains(&name.as_str()) {
+                let cycle = chain[chain.iter().position(|m| m == name).unwrap()..].iter().chain(std::iter::once(&name.as_str())).copied().collect::<Vec<&str>>();
+                errors.push(AssembleError::at_token(&mac[0], format!("macro `{}` ends up calling itself ({})", name, cycle.join(" -> "))));
+                continue;
+            }
+            if chain.len() >= options.max_macro_depth {
+                errors.push(AssembleError::at_token(&mac[0], format!("macro calls are nested more than {} deep (raise it with --macro-depth)", options.max_macro_depth)));
+                continue;
+            }
+            let Some(values) = match_args(&mac, params, errors) else { continue };
+            // a body using the ...rest param is repeated for each of its arguments (and left out if there aren't any)
+            let repeats = match params.iter().position(|param| param.2) {
+                Some(rest) if body.iter().flatten().any(|t| *t == params[rest].0) => values[rest].len(),
+                _ => 1,
+            };
+            expansions.lines += (body.len() * repeats).max(1);
+            if expansions.lines > options.max_expanded_lines {
+                // nothing after this gets expanded, since it'd most likely hit the limit again
+                errors.push(AssembleError::at_token(&mac[0], format!("macros expanded to more than {} lines (raise it with --max-expansion)", options.max_expanded_lines)));
+                return;
+            }
+            for repeat in 0..repeats {
+                // every repeat counts as its own expansion, so its local labels don't clash with the other repeats'
+                expansions.count += 1;
+                for line in body {
+                    // replacing any args (those keep pointing at the call, everything else remembers which call spliced it in)
+                    let new_line = line.iter().enumerate().map(|(index, t)| {
+                        if let Some(param_index) = params.iter().position(|param| *t == param.0) {
+                            let arg = &values[param_index][if params[param_index].2 { repeat } else { 0 }];
+                            // an arg starting a line can be a macro call, which needs the chain it's in to catch recursion through args
+                            if index == 0 { arg.expanded_by(name, &mac[0]) } else { arg.clone() }
+                        } else if let Some(label) = local_label(&t.text) {
+                            // each expansion gets its own copy of the label, so a macro can be used more than once
+                            t.expanded_by(name, &mac[0]).with_text(format!("{}~{}~{}", name, expansions.count, label))
+                        } else { t.expanded_by(name, &mac[0]) }
+                    }).collect::<Vec<Token>>();
+                    lines.insert(line_number, new_line);
+                    line_number += 1;
+                }
+            }
+            line_number = starting_line;  // making sure a macro can recursively expand additional macros
+            continue;
+        }
+        line_number += 1;
+    }
+}
+
+// `%%name` or `@name`, a header that only exists inside one expansion of a macro
+fn local_label(text: &str) -> Option<&str> {
+    text.strip_prefix("%%").or_else(|| text.strip_prefix('@')).filter(|label| !label.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::token::tokenize;
+
+    // the page's lines once its macros are expanded, and the error messages
+    fn expand(source: &str) -> (Vec<String>, Vec<String>) {
+        let mut errors = vec![];
+        let mut lines = tokenize("test.mca", source, &mut errors);
+        let macros = get_macros(&mut lines, &mut vec![], &mut errors);
+        expand_macro_calls(&mut lines, &macros, &mut Expansions::default(), &AssembleOptions::default(), &mut errors);
+        let lines = lines.iter().map(|line| line.iter().map(|t| t.text.as_str()).collect::<Vec<&str>>().join(" ")).collect();
+        (lines, errors.into_iter().map(|err| err.message).collect())
+    }
+
+    #[test]
+    fn arity() {
+        let (_, errors) = expand("!macro m a b\n!end\nm rda");
+        assert_eq!(errors, ["macro `m` takes 2 arguments (a, b), found 1"]);
+        let (_, errors) = expand("!macro m a b=1\n!end\nm rda rdb rdc");
+        assert_eq!(errors, ["macro `m` takes 1 to 2 arguments (a, b=1), found 3"]);
+        let (_, errors) = expand("!macro m a ...rest\n!end\nm");
+        assert_eq!(errors, ["macro `m` takes at least 1 argument (a, ...rest), found 0"]);
+        let (_, errors) = expand("!macro m a\n!end\nm");
+        assert_eq!(errors, ["macro `m` takes 1 argument (a), found 0"]);
+        let (_, errors) = expand("!macro m a b ...rest\n!end\nm rda");
+        assert_eq!(errors, ["macro `m` takes at least 2 arguments (a, b, ...rest), found 1"]);
+    }
+
+    #[test]
+    fn defaults() {
+        let (lines, errors) = expand("!macro clear reg=rda value=0\n    Ldi reg value\n!end\nclear\nclear rdb\nclear rdb 7");
+        assert!(errors.is_empty());
+        assert_eq!(lines, ["Ldi rda 0", "Ldi rdb 0", "Ldi rdb 7"]);
+    }
+
+    #[test]
+    fn missing_default_after_default() {
+        // used to panic when the call left out the param without a default
+        let (_, errors) = expand("!macro m a=1 b\n    Ldi a b\n!end\nm rda");
+        assert_eq!(errors, ["`b` needs a default value, since the parameter before it has one"]);
+    }
+
+    #[test]
+    fn variadic_repeats_the_body() {
+        let (lines, errors) = expand("!macro push_many ...regs\n    LodL regs\n    ThruL\n    PshO\n!end\npush_many rda rdb");
+        assert!(errors.is_empty());
+        assert_eq!(lines, ["LodL rda", "ThruL", "PshO", "LodL rdb", "ThruL", "PshO"]);
+        let (lines, _) = expand("!macro push_many ...regs\n    LodL regs\n!end\nKill\npush_many\nKill");
+        assert_eq!(lines, ["Kill", "Kill"]);
+    }
+
+    #[test]
+    fn qualified_args() {
+        let source = "!macro call function::page current=here\n    Goto function page current\n!end\n";
+        let (lines, errors) = expand(&format!("{}call Sprites::draw\ncall draw@Sprites main\ncall draw Sprites", source));
+        assert!(errors.is_empty());
+        assert_eq!(lines, ["Goto draw Sprites here", "Goto draw Sprites main", "Goto draw Sprites here"]);
+        let (_, errors) = expand(&format!("{}call draw", source));
+        assert_eq!(errors, ["macro `call` takes 2 to 3 arguments (function::page, current=here), found 1"]);
+    }
+
+    #[test]
+    fn recursion_through_args() {
+        let (_, errors) = expand("!macro twice x\n    x x\n!end\ntwice twice");
+        assert_eq!(errors, ["macro `twice` ends up calling itself (twice -> twice)"]);
+    }
+
+    #[test]
+    fn duplicate_definitions() {
+        let (_, errors) = expand("!macro foo\n!end\n!macro foo a\n!end");
+        assert_eq!(errors, ["macro `foo` is already defined at test.mca:1"]);
+    }
+
+    #[test]
+    fn local_labels_are_unique() {
+        let (lines, _) = expand("!macro m ...regs\n    !header %%skip\n    Jmp %%skip regs\n!end\nm rda rdb");
+        assert_eq!(lines, ["!header m~1~skip", "Jmp m~1~skip rda", "!header m~2~skip", "Jmp m~2~skip rdb"]);
+    }
+}
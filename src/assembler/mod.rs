@@ -0,0 +1,532 @@
+use crate::isa::{operand_shift, OP_CODES, PAGE_SIZE, REGISTERS};
+
+mod conditional;
+mod data;
+mod diagnostics;
+mod expr;
+mod include;
+mod jumps;
+mod macros;
+mod prelude;
+mod strict;
+mod token;
+mod variables;
+
+pub use diagnostics::{render_errors, AssembleError};
+use expr::ValueError;
+pub use token::{tokenize, Expansion, Span, Token};
+
+// name, line, page
+pub type Header = (String, usize, usize);
+
+// name, and the `!const` line's expression (worked out where it's used, so it can mention headers)
+pub type Constant = (String, Token);
+
+// name, address, size in bytes, and whether it's on disc (`!dvar`) rather than in ram (`!var`)
+pub type Variable = (String, usize, usize, bool);
+
+#[derive(Debug, Clone, Default)]
+pub struct Page {
+    pub name: String,
+    pub words: Vec<u32>,
+    // every line of the page after macros and names were expanded (including the ones that aren't instructions)
+    pub tokens: Vec<Vec<String>>,
+    // the tokens behind each word in words
+    pub instructions: Vec<Vec<String>>,
+    // the mnemonic token of each word, which knows the source line and macro calls it came from
+    pub origins: Vec<Token>,
+    // the header each word's Jmp/Jiz/Jnz/Goto was written with (None for everything else, or a raw line number)
+    pub targets: Vec<Option<Header>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Program {
+    pub pages: Vec<Page>,
+    pub headers: Vec<Header>,
+    // the names of the exported (`!macro -export`) macros
+    pub macros: Vec<String>,
+    // things that assemble fine but probably aren't what was meant (like jumps into the wrong page)
+    pub warnings: Vec<AssembleError>,
+    // every file the program was assembled from, as (file, source), for pointing back at the original lines
+    pub sources: Vec<(String, String)>,
+    // what ram and disc start out holding, from the `!ram`/`!disc` lines (256 bytes each, or empty if there weren't any)
+    pub ram: Vec<u8>,
+    pub disc: Vec<u8>,
+    // where every `!var` and `!dvar` ended up
+    pub variables: Vec<Variable>,
+}
+
+impl Program {
+    // the raw words of every page, in the layout the emulator expects
+    pub fn page_words(&self) -> Vec<Vec<u32>> {
+        self.pages.iter().map(|page| page.words.clone()).collect()
+    }
+
+    pub fn total_words(&self) -> usize {
+        self.pages.iter().map(|page| page.words.len()).sum()
+    }
+
+    // the original text of a line in one of the source files
+    pub fn source_line(&self, span: &Span) -> Option<&str> {
+        let (_, source) = self.sources.iter().find(|(file, _)| **file == *span.file)?;
+        source.lines().nth(span.line.checked_sub(1)?)
+    }
+}
+
+// the switches that change how a script gets assembled
+#[derive(Debug, Clone)]
+pub struct AssembleOptions {
+    // splits pages with more than 256 instructions across extra pages instead of erroring
+    pub auto_page: bool,
+    // how deep macros can call other macros
+    pub max_macro_depth: usize,
+    // how many lines all the macro expansions together can add, so runaway expansion fails fast
+    pub max_expanded_lines: usize,
+    // names set before the script starts, like `!define NAME value` at the top (from -D NAME=value)
+    pub defines: Vec<(String, String)>,
+    // errors on lines that don't start with an instruction (which are otherwise skipped as comments),
+    // and on instructions given too many operands (a `!strict` line in the script turns this on as well)
+    pub strict: bool,
+}
+
+impl Default for AssembleOptions {
+    fn default() -> AssembleOptions {
+        AssembleOptions {
+            auto_page: false,
+            max_macro_depth: 64,
+            max_expanded_lines: 100_000,
+            defines: vec![],
+            strict: false,
+        }
+    }
+}
+
+// assembles a full script into its pages of bytecode
+pub fn assemble(source: &str) -> Result<Program, Vec<AssembleError>> {
+    assemble_named("<source>", source)
+}
+
+// the same as assemble, but errors point at the given file name
+pub fn assemble_named(file: &str, source: &str) -> Result<Program, Vec<AssembleError>> {
+    assemble_with(file, source, &AssembleOptions::default())
+}
+
+pub fn assemble_with(file: &str, source: &str, options: &AssembleOptions) -> Result<Program, Vec<AssembleError>> {
+    let mut errors = vec![];
+    let mut pages = vec![(vec![], "main".to_string())];
+    let mut global_macros = vec![];
+    let mut sources = vec![];
+    let mut strict = options.strict;
+    for line in include::load(file, source, options.defines.clone(), &mut sources, &mut errors) {
+        if line[0] == "!page" {
+            match line.get(1) {
+                Some(name) => pages.push((vec![], name.text.clone())),
+                None => errors.push(AssembleError::after_line(&line, String::from("expected a name for the page"))),
+            }
+        }
+        else if line[0] == "!strict" { strict = true; }
+        else { pages.last_mut().unwrap().0.push(line); }
+    }
+    let mut expansions = macros::Expansions::default();
+    for script in pages.iter_mut() {
+        // collecting all macros
+        let macros = macros::get_macros(&mut script.0, &mut global_macros, &mut errors);
+        macros::expand_macro_calls(&mut script.0, &macros, &mut expansions, options, &mut errors);
+        macros::expand_macro_calls(&mut script.0, &global_macros, &mut expansions, options, &mut errors);
+        if strict {
+            let names = macros.iter().chain(&global_macros).map(|(name, ..)| name.as_str()).collect::<Vec<&str>>();
+            strict::check_page(&script.0, &names, &mut errors);
+        }
+    }
+    let constants = get_constants(&mut pages, &mut errors);
+    // variable sizes and a `!fill`'s count can use constants, but not headers (since where they land depends on them)
+    let page_names = pages.iter().map(|(_, name)| name.clone()).collect::<Vec<String>>();
+    let header_names = header_names(&pages);
+    // with --auto-page the pages can still move, so those can't use page names either
+    let names = Names { headers: &[], constants: &constants, variables: &[], page_names: &page_names, page: 0, unsplit: options.auto_page };
+    let taken = |name: &str| taken_name(name, &header_names, &page_names).or(constants.iter().any(|(constant, _)| constant == name).then_some("constant"));
+    let variables = variables::get_variables(&mut pages, &|text| names.evaluate(text), &taken, &mut errors);
+    let names = Names { variables: &variables, ..names };
+    for (page, script) in pages.iter_mut().enumerate() {
+        let names = Names { page, ..names };
+        data::expand_data(&mut script.0, &|text| names.evaluate(text), &mut errors);
+    }
+    if options.auto_page {
+        pages = pages.into_iter().flat_map(split_page).collect();
+    }
+    let mut headers = vec![];
+    for (page, script) in pages.iter().enumerate() {
+        headers.append(&mut generate_headers(&script.0, page, &mut errors));
+    }
+    let page_names = pages.iter().map(|(_, name)| name.clone()).collect::<Vec<String>>();
+    let image_value = |text: &str, page: usize| operand_value(text, &Names { headers: &headers, constants: &constants, variables: &variables, page_names: &page_names, page, unsplit: false });
+    let ram = data::memory_image("!ram", &pages, &image_value, &mut errors);
+    let disc = data::memory_image("!disc", &pages, &image_value, &mut errors);
+
+    let mut program = Program::default();
+    for script_index in 0..pages.len() {
+        program.pages.push(compile_script(&mut pages, &headers, &constants, &variables, script_index, &mut errors));
+    }
+    for page in &program.pages {
+        if page.words.len() > PAGE_SIZE {
+            let mut err = AssembleError::at_token(&page.origins[PAGE_SIZE],
+                format!("page `{}` has {} instructions, but a page only holds {}", page.name, page.words.len(), PAGE_SIZE));
+            err.notes.push(String::from("this is the first instruction that doesn't fit; move some to another `!page` or use --auto-page"));
+            errors.push(err);
+        }
+    }
+    program.headers = headers;
+    program.macros = global_macros.iter().map(|(name, ..)| name.clone()).collect();
+    program.sources = sources;
+    program.ram = ram;
+    program.disc = disc;
+    program.variables = variables;
+
+    if !errors.is_empty() {
+        errors.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        return Err(errors.into_iter().map(|err| err.with_source(&program.sources)).collect());
+    }
+    program.warnings = jumps::check_page_register(&program).into_iter().map(|warning| warning.with_source(&program.sources)).collect();
+    Ok(program)
+}
+
+// turns a page's lines into bytecode, remembering where each instruction came from
+fn compile_script(pages: &mut [(Vec<Vec<Token>>, String)], headers: &[Header], constants: &[Constant], variables: &[Variable],
+        script_index: usize, errors: &mut Vec<AssembleError>) -> Page {
+    let page_names = pages.iter().map(|(_, name)| name.clone()).collect::<Vec<String>>();
+    let names = Names { headers, constants, variables, page_names: &page_names, page: script_index, unsplit: false };
+    let mut line_targets = vec![None; pages[script_index].0.len()];
+    for (line_index, line) in pages[script_index].0.iter_mut().enumerate() {
+        let is_jump = ["Jmp", "Jiz", "Jnz", "Goto"].iter().any(|op| line[0] == *op);
+        // only operands can be qualified, so comments are left alone
+        let operand_count = OP_CODES.iter().find(|op| line[0] == op.3).map_or(0, |op| op.1);
+        let mut token_index = 0;
+        while token_index < line.len() {
+            let token = &line[token_index];
+            // a qualified header inside an expression (like main::loop+1) is left for operand_value
+            let qualified = if (1..=operand_count).contains(&token_index) && token.text.chars().all(expr::is_name_char) {
+                split_qualified(&token.text)
+            } else { None };
+            if let Some((page_name, header_name)) = qualified {
+                let page = page_names.iter().position(|name| name == page_name);
+                let header = headers.iter().find(|h| h.0 == header_name && Some(h.2) == page);
+                let value = match (page, header) {
+                    (None, _) => { errors.push(AssembleError::at_token(token, format!("no page named `{}`", page_name))); 0 },
+                    (Some(_), None) => { errors.push(AssembleError::at_token(token, format!("no header named `{}` on page `{}`", header_name, page_name))); 0 },
+                    (Some(_), Some(header)) => {
+                        if is_jump && token_index == 1 { line_targets[line_index] = Some(header.clone()); }
+                        header.1
+                    },
+                };
+                // a Goto only needs the one operand, since the page comes along with the header
+                if line[0] == "Goto" && token_index == 1 {
+                    line.insert(2, token.with_text(page.unwrap_or_default().to_string()));
+                }
+                line[token_index] = line[token_index].with_text(value.to_string());
+            }
+            else if let Some(reg_index) = REGISTERS.iter().position(|r| token == r) {
+                line[token_index] = token.with_text(reg_index.to_string());
+            }
+            else if let Some(header) = find_header(line, token_index, &page_names, headers, script_index) {
+                if header.2 != script_index && ["Jmp", "Jiz", "Jnz"].iter().any(|op| line[0] == *op) && token_index == 1 {
+                    let mut err = AssembleError::at_token(token, format!("`{}` is a header on page `{}`, but `{}` stays on the page it's in (`{}`)",
+                        header.0, page_names[header.2], line[0], page_names[script_index]));
+                    err.notes.push(format!("use `Goto {}::{}`, or write `{}::{}` if the page register was already set to it", page_names[header.2], header.0, page_names[header.2], header.0));
+                    errors.push(err);
+                }
+                if is_jump && token_index == 1 { line_targets[line_index] = Some(header.clone()); }
+                line[token_index] = token.with_text(header.1.to_string());
+            }
+            else if let Some(page_index) = page_names.iter().position(|page_name| token == page_name) {
+                line[token_index] = token.with_text(page_index.to_string());
+            }
+            token_index += 1;
+        }
+    }
+    let mut page = Page {
+        name: pages[script_index].1.clone(),
+        tokens: pages[script_index].0.iter().map(|line| line.iter().map(|t| t.text.clone()).collect()).collect(),
+        ..Page::default()
+    };
+    for (line, target) in pages[script_index].0.iter().zip(line_targets) {
+        // replacing any headers mentioned with their index
+        let op = OP_CODES.iter().find(|(_, _, _, name)| line[0] == *name);
+        if let Some(op) = op {
+            let mut instruction = (op.0 as u32) << 24;
+            for i in 0..op.1 {
+                let Some(token) = line.get(i + 1) else {
+                    errors.push(AssembleError::after_line(line, format!("`{}` expects {} operand{}, found {}", op.3, op.1, if op.1 == 1 { "" } else { "s" }, line.len() - 1)));
+                    break;
+                };
+                match operand_value(&token.text, &names) {
+                    Ok(value) => { instruction |= (value as u32) << operand_shift(i, op.2[i]); },
+                    Err(message) => errors.push(AssembleError::at_token(token, message)),
+                }
+            }
+            page.words.push(instruction);
+            page.instructions.push(line.iter().map(|t| t.text.clone()).collect());
+            page.origins.push(line[0].clone());
+            page.targets.push(target);
+        }
+        else if line[0] == "!byte" || line[0] == "!word" {
+            let value = if line[0] == "!byte" { operand_value(&line[1].text, &names).map(|value| value as u32) } else { word_value(&line[1].text, &names) };
+            page.words.push(value.unwrap_or_else(|message| {
+                errors.push(AssembleError::at_token(&line[1], message));
+                0
+            }));
+            page.instructions.push(line.iter().map(|t| t.text.clone()).collect());
+            page.origins.push(line[0].clone());
+            page.targets.push(None);
+        }
+    } page
+}
+
+// `page::header` or `header@page`
+fn split_qualified(text: &str) -> Option<(&str, &str)> {
+    let (page, header) = match text.split_once("::") {
+        Some(parts) => parts,
+        None => text.split_once('@').map(|(header, page)| (page, header))?,
+    };
+    if page.is_empty() || header.is_empty() { None } else { Some((page, header)) }
+}
+
+// the header a plain name refers to, preferring the one on the page being jumped to (for Goto) or the current page,
+// since different pages can reuse the same header names
+fn find_header<'a>(line: &[Token], token_index: usize, page_names: &[String], headers: &'a [Header], script_index: usize) -> Option<&'a Header> {
+    let target_page = match line.get(2) {
+        Some(page) if line[0] == "Goto" && token_index == 1 => page_names.iter().position(|name| page == name).or(page.text.parse().ok()),
+        _ => Some(script_index),
+    };
+    let token = &line[token_index];
+    headers.iter().find(|h| *token == h.0 && Some(h.2) == target_page)
+        .or_else(|| headers.iter().find(|h| *token == h.0))
+}
+
+// the byte an operand stands for, once the plain names in it were replaced
+// anything that's still not a number is worked out as an expression, like SCALE-1, 'w' or lo(header+2)
+fn operand_value(text: &str, names: &Names) -> Result<u8, String> {
+    if let Ok(value) = text.parse::<u8>() { return Ok(value); }
+    let value = if is_valid_name(text) {
+        names.value(text, &[]).map_err(|error| match error {
+            // a lone word that isn't anything is more likely a typo than a broken expression
+            ValueError::Undefined(_) if text.len() == 3 && text.starts_with("rd") => format!("unknown register `{}` (expected rda through rdp)", text),
+            ValueError::Undefined(_) => format!("expected a register, header, page, constant, variable or number, found `{}`", text),
+            ValueError::Other(message) => message,
+        })?
+    } else { names.evaluate(text)? };
+    // negative numbers are stored as two's complement
+    match value {
+        0..=255 => Ok(value as u8),
+        -128..=-1 => Ok(value as i8 as u8),
+        _ if text.parse::<i64>().is_ok() => Err(format!("`{}` doesn't fit in 8 bits (-128 to 255)", text)),
+        _ => Err(format!("`{}` is {}, which doesn't fit in 8 bits (-128 to 255)", text, value)),
+    }
+}
+
+// the value of a `!word`, which can take up the whole 32 bits
+fn word_value(text: &str, names: &Names) -> Result<u32, String> {
+    let value = names.evaluate(text)?;
+    match value {
+        0..=0xFFFF_FFFF => Ok(value as u32),
+        -0x8000_0000..=-1 => Ok(value as i32 as u32),
+        _ => Err(format!("`{}` is {}, which doesn't fit in 32 bits", text, value)),
+    }
+}
+
+// everything a name in an operand expression can be
+struct Names<'a> {
+    headers: &'a [Header],
+    constants: &'a [Constant],
+    variables: &'a [Variable],
+    page_names: &'a [String],
+    page: usize,  // the page being compiled, whose headers win over ones with the same name elsewhere
+    unsplit: bool,  // --auto-page hasn't split the pages yet, so the page indexes could still move
+}
+
+impl Names<'_> {
+    // an operand expression, with its names looked up here
+    fn evaluate(&self, text: &str) -> Result<i64, String> {
+        expr::evaluate(text, &|name| self.value(name, &[]))
+    }
+
+    // chain is the constants already being worked out, for catching ones defined in terms of themselves
+    fn value(&self, name: &str, chain: &[&str]) -> Result<i64, ValueError> {
+        if let Some((constant, expression)) = self.constants.iter().find(|(constant, _)| constant == name) {
+            if chain.contains(&name) {
+                let cycle = chain.iter().skip_while(|other| **other != name).chain(std::iter::once(&name)).copied().collect::<Vec<&str>>();
+                return Err(ValueError::Other(format!("the constant `{}` is defined in terms of itself ({})", name, cycle.join(" -> "))));
+            }
+            let chain = chain.iter().copied().chain(std::iter::once(constant.as_str())).collect::<Vec<&str>>();
+            return expr::evaluate(&expression.text, &|name| self.value(name, &chain)).map_err(ValueError::Other);
+        }
+        if let Some(variable) = self.variables.iter().find(|variable| variable.0 == name) { return Ok(variable.1 as i64); }
+        if let Some(register) = REGISTERS.iter().position(|register| *register == name) { return Ok(register as i64); }
+        if let Some((page_name, header_name)) = split_qualified(name) {
+            return self.headers.iter()
+                .find(|h| h.0 == header_name && self.page_names.get(h.2).is_some_and(|page| page == page_name))
+                .map(|h| h.1 as i64)
+                .ok_or_else(|| ValueError::Other(format!("no header named `{}` on page `{}`", header_name, page_name)));
+        }
+        let header = self.headers.iter().find(|h| h.0 == name && h.2 == self.page).or_else(|| self.headers.iter().find(|h| h.0 == name));
+        if let Some(header) = header { return Ok(header.1 as i64); }
+        let page = self.page_names.iter().position(|page| page == name)
+            // the page the line ends up on, so `call` can push the page to come back to without being told it
+            .or((name == "current_page").then_some(self.page));
+        if let Some(page) = page {
+            if self.unsplit { return Err(ValueError::Other(format!("`{}` is a page, whose index isn't known until --auto-page has split the pages", name))); }
+            return Ok(page as i64);
+        }
+        Err(ValueError::Undefined(name.to_string()))
+    }
+}
+
+// the different names a header can be made with
+static HEADER_DIRECTIVES: &[&str] = &["!header", "!end", "!loop"];
+
+// whether the line ends up as a word in the page, which is every instruction, and the data from `!byte`, `!string`, ...
+// (by the time this is used, data directives have been split into a `!byte` or `!word` line for each word)
+fn takes_word(line: &[Token]) -> bool {
+    OP_CODES.iter().any(|(_, _, _, name)| line[0] == *name) || line[0] == "!byte" || line[0] == "!word"
+}
+
+// splits a page too big for its bank into pages of at most 256 instructions (named like main~1, main~2, ...)
+// each full page ends with a Goto to the start of the next one, since running off the end of a bank doesn't
+// set the page register, so a later Jmp would land back in the old page
+// jumps to headers that ended up on another page still need a Goto
+fn split_page((mut script, name): (Vec<Vec<Token>>, String)) -> Vec<(Vec<Vec<Token>>, String)> {
+    let mut pages = vec![];
+    while script.iter().filter(|line| takes_word(line)).count() > PAGE_SIZE {
+        // leaving room for the Goto (which sets the page register itself)
+        let cut = script.iter()
+            .enumerate()
+            .filter(|(_, line)| takes_word(line))
+            .nth(PAGE_SIZE - 2)
+            .map(|(index, _)| index + 1)
+            .unwrap();
+        let rest = script.split_off(cut);
+        let next_name = format!("{}~{}", name, pages.len() + 1);
+        let last = script[cut - 1][0].clone();
+        script.push(vec![last.with_text(String::from("Goto")), last.with_text(String::from("0")), last.with_text(next_name.clone())]);
+        let page_name = if pages.is_empty() { name.clone() } else { format!("{}~{}", name, pages.len()) };
+        pages.push((script, page_name));
+        script = rest;
+    }
+    let page_name = if pages.is_empty() { name } else { format!("{}~{}", name, pages.len()) };
+    pages.push((script, page_name));
+    pages
+}
+
+// takes out every `!const NAME expression` line, which works across all pages wherever it's written
+fn get_constants(pages: &mut [(Vec<Vec<Token>>, String)], errors: &mut Vec<AssembleError>) -> Vec<Constant> {
+    let page_names = pages.iter().map(|(_, name)| name.clone()).collect::<Vec<String>>();
+    let header_names = header_names(pages);
+    let mut constants: Vec<Constant> = vec![];
+    for (script, _) in pages.iter_mut() {
+        for line in script.iter().filter(|line| line[0] == "!const") {
+            let (Some(name), Some(value)) = (line.get(1), line.get(2)) else {
+                errors.push(AssembleError::after_line(line, String::from("expected a name and a value after `!const`")));
+                continue;
+            };
+            if !is_valid_name(&name.text) {
+                errors.push(AssembleError::at_token(name, format!("`{}` isn't a valid constant name (only letters, digits and _)", name)));
+            }
+            else if let Some(kind) = taken_name(&name.text, &header_names, &page_names) {
+                errors.push(AssembleError::at_token(name, format!("the constant `{}` has the same name as a {}", name, kind)));
+            }
+            else if redeclared(name, "constant", constants.iter().find(|(constant, _)| *name == *constant)
+                    .map(|(_, other)| (other.text.clone(), format!("defined as `{}` at {}", other, other.span))), value.text.clone(), errors) {
+                continue;
+            }
+            else { constants.push((name.text.clone(), value.clone())); }
+        }
+        script.retain(|line| line[0] != "!const");
+    } constants
+}
+
+// the name of every header, found before headers are worked out so constants and variables can't clash with them
+fn header_names(pages: &[(Vec<Vec<Token>>, String)]) -> Vec<String> {
+    pages.iter()
+        .flat_map(|(script, _)| script.iter().filter(|line| HEADER_DIRECTIVES.iter().any(|h| line[0] == *h)).filter_map(|line| line.get(1)))
+        .map(|name| name.text.clone())
+        .collect()
+}
+
+// what a name is already used for, if it's a register, header or page
+fn taken_name(name: &str, header_names: &[String], page_names: &[String]) -> Option<&'static str> {
+    [
+        ("register", REGISTERS.contains(&name)),
+        ("header", header_names.iter().any(|header| name == header)),
+        ("page", page_names.iter().any(|page| name == page)),
+    ].iter().find(|(_, clashes)| *clashes).map(|(kind, _)| *kind)
+}
+
+// including the same file twice declares its constants and variables again, which is fine as long as they agree with themselves
+// gives whether the name was declared before (with a description of how), erroring if it was declared as something else
+fn redeclared<T: PartialEq>(name: &Token, kind: &str, earlier: Option<(T, String)>, value: T, errors: &mut Vec<AssembleError>) -> bool {
+    let Some((earlier, description)) = earlier else { return false };
+    if earlier != value {
+        errors.push(AssembleError::at_token(name, format!("the {} `{}` was already {}", kind, name, description)));
+    } true
+}
+
+// names given to constants and variables can't start with a digit, so they're never mistaken for numbers
+fn is_valid_name(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_ascii_digit()) && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn generate_headers(script: &[Vec<Token>], page: usize, errors: &mut Vec<AssembleError>) -> Vec<Header> {
+    // calculating header indexes
+    let mut true_index = 0;
+    let mut headers = vec![];
+    for line in script {
+        // checking for a header defintion
+        if HEADER_DIRECTIVES.iter().any(|h| line[0] == *h) {
+            // getting the name
+            match line.get(1) {
+                Some(name) => headers.push((name.text.clone(), true_index, page)),
+                None => errors.push(AssembleError::after_line(line, format!("expected a name after `{}`", line[0]))),
+            }
+            continue;  // this isn't a valid instruction and as such shouldn't be included in the bytecode
+        }
+        // invalid instruction, skipping (maybe a comment or something)
+        if !takes_word(line) {  continue; }
+        true_index += 1;
+    } headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble_with, AssembleOptions};
+    use crate::disasm::decode;
+
+    #[test]
+    fn auto_page_ends_full_pages_with_a_goto() {
+        let source = format!("LdiL 1\n{}Kill", "Inc\n".repeat(300));
+        let options = AssembleOptions { auto_page: true, ..AssembleOptions::default() };
+        let program = assemble_with("test.mca", &source, &options).unwrap();
+        assert_eq!(program.pages.iter().map(|page| (page.name.as_str(), page.words.len())).collect::<Vec<_>>(), [("main", 256), ("main~1", 47)]);
+        assert_eq!(decode(program.pages[0].words[254]).unwrap().0, "Inc");
+        assert_eq!(decode(program.pages[0].words[255]), Some(("Goto", vec![0, 1])));
+    }
+
+    #[test]
+    fn auto_page_rejects_page_indexes_it_could_still_move() {
+        let source = "!const OTHER other\n!var buffer OTHER\n!fill OTHER\nKill\n!page other\nKill";
+        assert!(assemble_with("test.mca", source, &AssembleOptions::default()).is_ok());
+        let options = AssembleOptions { auto_page: true, ..AssembleOptions::default() };
+        let errors = assemble_with("test.mca", source, &options).unwrap_err();
+        assert_eq!(errors.iter().map(|error| error.message.as_str()).collect::<Vec<_>>(),
+            ["`other` is a page, whose index isn't known until --auto-page has split the pages"; 2]);
+    }
+
+    #[test]
+    fn undefined_operands() {
+        let messages = |source: &str| assemble_with("test.mca", source, &AssembleOptions::default()).unwrap_err()
+            .into_iter().map(|error| error.message).collect::<Vec<String>>();
+        assert_eq!(messages("LodL acc"), ["expected a register, header, page, constant, variable or number, found `acc`"]);
+        assert_eq!(messages("LodL rdz"), ["unknown register `rdz` (expected rda through rdp)"]);
+        // a name that is defined, but as something broken, keeps what's wrong with it
+        assert_eq!(messages("!const A B\nLodL A"), ["`B` isn't defined"]);
+        assert_eq!(messages("LdiL acc+1"), ["`acc` isn't defined"]);
+    }
+}
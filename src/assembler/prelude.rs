@@ -0,0 +1,20 @@
+// the libraries built into the assembler, pulled in with `!use name` (the latest version) or `!use name version`
+// old versions stick around so a program keeps assembling the same way
+// name, version, source
+pub static PRELUDES: &[(&str, usize, &str)] = &[
+    ("std", 1, include_str!("prelude/std_v1.mca")),
+];
+
+// the file name errors and listings show for the library, and its source
+pub fn find(name: &str, version: Option<usize>) -> Result<(String, &'static str), String> {
+    let versions = PRELUDES.iter().filter(|prelude| prelude.0 == name).collect::<Vec<_>>();
+    let Some(latest) = versions.iter().map(|prelude| prelude.1).max() else {
+        let names = PRELUDES.iter().map(|prelude| prelude.0).collect::<Vec<&str>>();
+        return Err(format!("no built-in library named `{}` (there's {})", name, names.join(", ")));
+    };
+    let version = version.unwrap_or(latest);
+    match versions.iter().find(|prelude| prelude.1 == version) {
+        Some((_, _, source)) => Ok((format!("<{} v{}>", name, version), source)),
+        None => Err(format!("`{}` has no version {} (the latest is {})", name, version, latest)),
+    }
+}
@@ -0,0 +1,109 @@
+use super::data;
+use super::diagnostics::AssembleError;
+use super::token::Token;
+use crate::isa::OP_CODES;
+
+// the lines a page keeps once macros are expanded that aren't instructions (besides these) are skipped as comments,
+// unless the program is strict, where they're errors instead
+static DIRECTIVES: &[&str] = &["!header", "!end", "!loop", "!const", "!var", "!dvar"];
+
+// errors for every line of a page (after macro expansion) that doesn't start with an instruction or directive,
+// and for instructions given more operands than they take
+// macros is every macro the page could have called, so a misspelled call gets a suggestion too
+pub fn check_page(script: &[Vec<Token>], macros: &[&str], errors: &mut Vec<AssembleError>) {
+    for line in script {
+        if DIRECTIVES.iter().chain(data::DIRECTIVES).chain(data::MEMORY_DIRECTIVES).any(|directive| line[0] == *directive) { continue; }
+        let Some(op) = OP_CODES.iter().find(|op| line[0] == op.3) else {
+            let kind = if line[0].text.starts_with('!') { "directive" } else { "instruction" };
+            let mut err = AssembleError::at_token(&line[0], format!("unknown {} `{}`", kind, line[0]));
+            let names = OP_CODES.iter().map(|op| op.3).chain(macros.iter().copied()).chain(DIRECTIVES.iter().chain(data::DIRECTIVES).chain(data::MEMORY_DIRECTIVES).copied());
+            if let Some(suggestion) = nearest(&line[0].text, names) {
+                err.notes.push(format!("did you mean `{}`?", suggestion));
+            }
+            errors.push(err);
+            continue;
+        };
+        if let Some(extra) = line.get(op.1 + 1) {
+            let found = line.len() - 1;
+            errors.push(AssembleError::at_token(extra, format!("`{}` takes {} operand{}, found {}",
+                op.3, op.1, if op.1 == 1 { "" } else { "s" }, found)));
+        }
+    }
+}
+
+// the name closest to the misspelled one, if any is close enough to be what was meant
+fn nearest<'a>(name: &str, names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    let length = name.chars().count();
+    // on a tie, the one with the same letters wins (LdoL is LodL with two letters swapped, rather than LdiL with one changed)
+    names.map(|candidate| (edit_distance(&name.to_lowercase(), &candidate.to_lowercase()), letters(name) != letters(candidate), candidate))
+        .filter(|(distance, ..)| *distance <= 2 && *distance < length)
+        .min_by_key(|(distance, different_letters, _)| (*distance, *different_letters))
+        .map(|(.., candidate)| candidate)
+}
+
+fn letters(text: &str) -> Vec<char> {
+    let mut letters = text.to_lowercase().chars().collect::<Vec<char>>();
+    letters.sort();
+    letters
+}
+
+// how many characters have to be inserted, removed, replaced or swapped with their neighbour to turn a into b
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<char>>();
+    let b = b.chars().collect::<Vec<char>>();
+    // distances[i][j] is the distance between the first i characters of a and the first j of b
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() { row[0] = i; }
+    for (j, distance) in distances[0].iter_mut().enumerate() { *distance = j; }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            let mut distance = (distances[i - 1][j] + 1).min(distances[i][j - 1] + 1).min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::token::tokenize;
+
+    #[test]
+    fn distance() {
+        assert_eq!(edit_distance("LdiL", "LdiL"), 0);
+        assert_eq!(edit_distance("LdiL", "LdiR"), 1);
+        assert_eq!(edit_distance("Ldi", "LdiL"), 1);
+        assert_eq!(edit_distance("LdoL", "LodL"), 1);  // swapped neighbours count once
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "Kill"), 4);
+    }
+
+    #[test]
+    fn suggestions() {
+        let names = ["LdiL", "LodL", "Kill", "Ldi", "call"];
+        assert_eq!(nearest("LdoL", names.iter().copied()), Some("LodL"));
+        assert_eq!(nearest("kill", names.iter().copied()), Some("Kill"));
+        assert_eq!(nearest("cal", names.iter().copied()), Some("call"));
+        assert_eq!(nearest("Jmp", names.iter().copied()), None);
+        // a name can't be all typo
+        assert_eq!(nearest("ab", ["Lx"].into_iter()), None);
+    }
+
+    #[test]
+    fn check() {
+        let mut errors = vec![];
+        let script = tokenize("test.mca", "Ldi rda 1\nLdoL rda\nLdiR 1 2\n!header start\nmy_macr", &mut errors);
+        check_page(&script, &["my_macro"], &mut errors);
+        let messages = errors.iter().map(|err| (err.message.as_str(), err.notes.clone())).collect::<Vec<_>>();
+        assert_eq!(messages, [
+            ("unknown instruction `LdoL`", vec![String::from("did you mean `LodL`?")]),
+            ("`LdiR` takes 1 operand, found 2", vec![]),
+            ("unknown instruction `my_macr`", vec![String::from("did you mean `my_macro`?")]),
+        ]);
+    }
+}
@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use super::diagnostics::AssembleError;
+
+// where a piece of text sits in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub file: Arc<str>,
+    pub line: usize,    // 1 based
+    pub column: usize,  // 1 based, in characters
+    pub length: usize,  // in characters
+}
+
+impl std::fmt::Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+// a macro invocation that a token was spliced in by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub name: String,
+    pub call: Span,
+}
+
+impl std::fmt::Display for Expansion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "in macro `{}` called from {}", self.name, self.call)
+    }
+}
+
+// a single piece of the source, remembering where it came from for error messages and listings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text: String,
+    pub span: Span,  // keeps the original position/length, even after the text has been replaced
+    // the macro calls this token was expanded through, innermost first (empty if it was written directly)
+    pub expansions: Arc<Vec<Expansion>>,
+}
+
+impl Token {
+    pub fn new(text: &str, span: Span) -> Token {
+        Token { text: text.to_string(), span, expansions: Arc::new(vec![]) }
+    }
+
+    // the same position, but different text (used when names get replaced with their values)
+    pub fn with_text(&self, text: String) -> Token {
+        Token { text, ..self.clone() }
+    }
+
+    // a copy of this token as if it was spliced in by a call to the given macro
+    pub fn expanded_by(&self, name: &str, call: &Token) -> Token {
+        let mut expansions = vec![Expansion { name: name.to_string(), call: call.span.clone() }];
+        expansions.extend(call.expansions.iter().cloned());
+        Token { expansions: Arc::new(expansions), ..self.clone() }
+    }
+}
+
+impl PartialEq<str> for Token {
+    fn eq(&self, other: &str) -> bool { self.text == other }
+}
+
+impl PartialEq<&str> for Token {
+    fn eq(&self, other: &&str) -> bool { self.text == *other }
+}
+
+impl PartialEq<String> for Token {
+    fn eq(&self, other: &String) -> bool { self.text == *other }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+// splits the source into lines of tokens (lines left empty are dropped)
+// any whitespace separates tokens, and `; comments`, `# comments`, `// comments` and `/* comments */` (which can span lines)
+// are dropped wherever they start, except inside "strings" and 'c' character literals, which are kept as one token (quotes included)
+// (a character literal can only be an operand, or part of one)
+pub fn tokenize(file: &str, source: &str, errors: &mut Vec<AssembleError>) -> Vec<Vec<Token>> {
+    let file: Arc<str> = Arc::from(file);
+    let mut script = vec![];
+    let mut open_comment = None;  // where the /* */ comment being skipped started
+    for (line_index, line) in source.lines().enumerate() {
+        let chars = line.chars().collect::<Vec<char>>();
+        let span = |start: usize, end: usize| Span { file: file.clone(), line: line_index + 1, column: start + 1, length: end - start };
+        let mut tokens = vec![];
+        let mut start = None;
+        let mut quote = None;  // the quote the current token's string or character literal opened with, and where
+        let mut column = 0;
+        while column < chars.len() {
+            let c = chars[column];
+            let next = chars.get(column + 1).copied();
+            if open_comment.is_some() {
+                if c == '*' && next == Some('/') {
+                    open_comment = None;
+                    column += 1;
+                }
+            }
+            else if let Some((quote_char, _)) = quote {
+                if c == '\\' { column += 1; }  // skipping whatever's escaped, so \" doesn't close the string
+                else if c == quote_char { quote = None; }
+            }
+            else if c.is_whitespace() || c == ';' || c == '#' || (c == '/' && (next == Some('/') || next == Some('*'))) {
+                if let Some(start) = start.take() {
+                    tokens.push(Token::new(&chars[start..column].iter().collect::<String>(), span(start, column)));
+                }
+                if c == '/' && next == Some('*') {
+                    open_comment = Some(span(column, column + 2));
+                    column += 1;
+                }
+                else if !c.is_whitespace() { break; }
+            }
+            else {
+                // an apostrophe only starts a character literal where an operand's value can start, so it's just part of the word
+                // in prose like "don't" (lines that don't start with an instruction are skipped as comments)
+                let operand_start = !tokens.is_empty() && (start.is_none() || "(+-*/%&|^~!<>=,".contains(chars[column - 1]));
+                if c == '"' || (c == '\'' && operand_start) { quote = Some((c, column)); }
+                if start.is_none() { start = Some(column); }
+            }
+            column += 1;
+        }
+        if let Some((_, quote_start)) = quote {
+            let what = if chars[quote_start] == '"' { "string" } else { "character literal" };
+            errors.push(AssembleError::new(&span(quote_start, chars.len()), format!("this {} is never closed", what)));
+        }
+        if let Some(start) = start {
+            let end = column.min(chars.len());
+            tokens.push(Token::new(&chars[start..end].iter().collect::<String>(), span(start, end)));
+        }
+        if !tokens.is_empty() { script.push(tokens); }
+    }
+    if let Some(span) = open_comment {
+        errors.push(AssembleError::new(&span, String::from("this comment is never closed with `*/`")));
+    } script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the text of every token on every line, and the error messages
+    fn lex(source: &str) -> (Vec<Vec<String>>, Vec<String>) {
+        let mut errors = vec![];
+        let lines = tokenize("test.mca", source, &mut errors);
+        let lines = lines.iter().map(|line| line.iter().map(|token| token.text.clone()).collect()).collect();
+        (lines, errors.into_iter().map(|err| err.message).collect())
+    }
+
+    #[test]
+    fn comments() {
+        let (lines, errors) = lex("Ldi rda 1 ; semicolon\nLdi rdb 2 # hash\nLdi rdc 3 // slashes\n; whole line\nKill;touching");
+        assert!(errors.is_empty());
+        assert_eq!(lines, [vec!["Ldi", "rda", "1"], vec!["Ldi", "rdb", "2"], vec!["Ldi", "rdc", "3"], vec!["Kill"]]);
+    }
+
+    #[test]
+    fn block_comments() {
+        let (lines, errors) = lex("Ldi /* inline */ rda 1\nKill /* starts here\nstill a comment\nends */ Nop");
+        assert!(errors.is_empty());
+        assert_eq!(lines, [vec!["Ldi", "rda", "1"], vec!["Kill"], vec!["Nop"]]);
+        let (_, errors) = lex("Kill /* never\nends");
+        assert_eq!(errors, ["this comment is never closed with `*/`"]);
+    }
+
+    #[test]
+    fn quotes() {
+        let (lines, errors) = lex("!string \"a ; b # c // d\" 'x' ';'\n!string \"say \\\"hi\\\"\"");
+        assert!(errors.is_empty());
+        assert_eq!(lines, [vec!["!string", "\"a ; b # c // d\"", "'x'", "';'"], vec!["!string", "\"say \\\"hi\\\"\""]]);
+        let (_, errors) = lex("!string \"open\nLdiR 'x");
+        assert_eq!(errors, ["this string is never closed", "this character literal is never closed"]);
+    }
+
+    #[test]
+    fn apostrophes_in_prose() {
+        let (lines, errors) = lex("Don't forget the player's score\nLdiR 'w'\nLdiL lo('a'+1)\nLdiL 1+'b' ; it's a comment");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(lines, [vec!["Don't", "forget", "the", "player's", "score"], vec!["LdiR", "'w'"], vec!["LdiL", "lo('a'+1)"], vec!["LdiL", "1+'b'"]]);
+    }
+
+    #[test]
+    fn spans() {
+        let lines = tokenize("test.mca", "  Ldi\trda  12", &mut vec![]);
+        let spans = lines[0].iter().map(|token| (token.span.line, token.span.column, token.span.length)).collect::<Vec<_>>();
+        assert_eq!(spans, [(1, 3, 3), (1, 7, 3), (1, 12, 2)]);
+    }
+}
@@ -0,0 +1,62 @@
+use super::diagnostics::AssembleError;
+use super::token::Token;
+use super::{is_valid_name, redeclared, Variable};
+
+// how many bytes ram and disc each hold
+const MEMORY_SIZE: usize = 256;
+
+// takes out every `!var name [size]` and `!dvar name [size]` line, giving each name the next free ram (or disc) addresses
+// in the order they're written; sizes default to 1 byte, and can use constants
+// taken says what else a name is already used for (like "register" or "header"), if anything
+pub fn get_variables(pages: &mut [(Vec<Vec<Token>>, String)], size: &dyn Fn(&str) -> Result<i64, String>,
+        taken: &dyn Fn(&str) -> Option<&'static str>, errors: &mut Vec<AssembleError>) -> Vec<Variable> {
+    let mut variables: Vec<Variable> = vec![];
+    let mut next = [0, 0];  // the next free ram and disc address
+    for (script, _) in pages.iter_mut() {
+        for line in script.iter().filter(|line| line[0] == "!var" || line[0] == "!dvar") {
+            let disc = line[0] == "!dvar";
+            let memory = if disc { "disc" } else { "ram" };
+            let Some(name) = line.get(1) else {
+                errors.push(AssembleError::after_line(line, format!("expected a name after `{}`", line[0])));
+                continue;
+            };
+            if let Some(extra) = line.get(3) {
+                errors.push(AssembleError::at_token(extra, format!("`{}` takes a name and a size", line[0])));
+            }
+            let bytes = match line.get(2).map(|token| (token, size(&token.text))) {
+                None => 1,
+                Some((_, Ok(bytes))) if (1..=MEMORY_SIZE as i64).contains(&bytes) => bytes as usize,
+                Some((token, Ok(bytes))) => {
+                    errors.push(AssembleError::at_token(token, format!("a variable takes 1 to {} bytes, not {}", MEMORY_SIZE, bytes)));
+                    continue;
+                },
+                Some((token, Err(message))) => {
+                    errors.push(AssembleError::at_token(token, message));
+                    continue;
+                },
+            };
+            if !is_valid_name(&name.text) {
+                errors.push(AssembleError::at_token(name, format!("`{}` isn't a valid variable name (only letters, digits and _)", name)));
+            }
+            else if let Some(kind) = taken(&name.text) {
+                errors.push(AssembleError::at_token(name, format!("the variable `{}` has the same name as a {}", name, kind)));
+            }
+            else if redeclared(name, "variable", variables.iter().find(|variable| *name == variable.0)
+                    .map(|(_, _, other_bytes, other_disc)| ((*other_bytes, *other_disc), format!("declared as {} byte{} of {}",
+                        other_bytes, if *other_bytes == 1 { "" } else { "s" }, if *other_disc { "disc" } else { "ram" }))), (bytes, disc), errors) {
+                continue;
+            }
+            else if next[disc as usize] + bytes > MEMORY_SIZE {
+                let mut err = AssembleError::at_token(name, format!("out of {}: `{}` needs {} byte{}, but only {} are left",
+                    memory, name, bytes, if bytes == 1 { "" } else { "s" }, MEMORY_SIZE - next[disc as usize]));
+                err.notes.push(format!("every `{}` together can take up to {} bytes; the listing (--listing) shows where they all went", line[0], MEMORY_SIZE));
+                errors.push(err);
+            }
+            else {
+                variables.push((name.text.clone(), next[disc as usize], bytes, disc));
+                next[disc as usize] += bytes;
+            }
+        }
+        script.retain(|line| line[0] != "!var" && line[0] != "!dvar");
+    } variables
+}
@@ -0,0 +1,201 @@
+use McAssembler::AssembleOptions;
+use McAssembler::output::Format;
+use McAssembler::schematic::{SchematicLayout, MAX_SPACING};
+
+// the different things the binary can do with a script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Assemble,
+    Run,
+    Disasm,
+    Check,
+    Circ,
+    New,
+}
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub command: Command,
+    pub input: String,
+    pub options: AssembleOptions,
+    pub output: Option<String>,
+    pub format: Option<Format>,  // guessed from the output's extension when not given
+    pub circ: Option<String>,  // the Logisim circuit for the circ command
+    pub roms: Vec<String>,     // the labels/locations of the ROMs the circ command fills, in page order
+    pub schematic: SchematicLayout,
+    pub symbols: Option<String>,  // the symbol file disasm names headers and pages from
+    pub write_symbols: Option<String>,  // where to write the symbol file of the assembled program
+    pub listing: Option<String>,  // where to write the .lst listing of the assembled program
+    pub ram_image: Option<String>,   // what ram starts out holding when running, instead of the program's !ram lines
+    pub disc_image: Option<String>,  // the same for disc
+    pub save_disc: Option<String>,   // where to write the disc once the program stops running
+    pub print_tokens: bool,  // the "Final Tokens" dump from compile_script
+    pub print_lines: bool,   // the per-line "index: [tokens]" prints from compile_script
+}
+
+pub static USAGE: &str = "\
+Usage: McAssembler <command> <input> [options]
+
+Commands:
+    assemble <input>   assembles the script, writing it to the output (or stdout)
+    run <input>        assembles the script and runs it in the emulator
+    disasm <input>     turns a file written by assemble (or a Logisim ROM image) back into assembly that reassembles to it
+    check <input>      assembles the script without writing anything
+    circ <input>       assembles the script and writes it into the ROM(s) of a Logisim circuit (--circ), saving the new circuit to the output
+    new <path>         starts a new script at the path, which is strict (see --strict) and uses the standard library
+
+Options:
+    -o, --output <path>   where assemble/disasm write their output (assemble also writes the !ram/!disc images next to it,
+                          as <name>.ram.img and <name>.disc.img)
+    -f, --format <name>   the format assemble writes/disasm reads (guessed from the file otherwise):
+                              words     one hex word per line, with a comment for each page
+                              bin       raw little endian words, each page padded to 256 words (.bin)
+                              bin-be    raw big endian words, each page padded to 256 words
+                              ihex      intel hex, with each page's records starting at page * 1024 (.hex, .ihex)
+                              logisim   a \"v2.0 raw\" image for a Logisim ROM's Load Image (.img)
+                              schem     a Sponge schematic of the ROM for WorldEdit, a bank for each page (.schem)
+    --strict              errors on lines that don't start with an instruction instead of skipping them as comments,
+                          and on instructions given too many operands (the same as a `!strict` line in the script)
+    --auto-page           splits pages with more than 256 instructions across extra pages instead of erroring
+    --macro-depth <n>     how deep macros can call other macros (default 64)
+    --max-expansion <n>   how many lines all macro expansions together can add (default 100000)
+    -D <name>[=<value>]   defines a name for !ifdef/!if before the script starts, like a `!define` (the value defaults to 1)
+    --listing <path>      also writes a listing (.lst) pairing every word with its address and source line
+    --symbols <path>      a symbol file for disasm to name the headers and pages with
+    --write-symbols <path>
+                          also writes the headers, pages and exported macros to a symbol file (JSON if it ends in .json)
+    --ram-image <path>    what run starts ram with instead of the script's !ram lines (a \"v2.0 raw\" image or raw bytes)
+    --disc-image <path>   the same for disc
+    --save-disc <path>    writes the disc to a file once run stops, so it can be loaded again with --disc-image
+    --circ <path>         the .circ file the circ command starts from
+    --rom <label>         a ROM for the circ command to fill, by label or location like (380,1260); can be given more than
+                          once to split the pages across several ROMs (defaults to the only ROM with a 16 bit address)
+    --one-block <id>      the block for a 1 bit in a schematic (default minecraft:redstone_block)
+    --zero-block <id>     the block for a 0 bit in a schematic (default minecraft:glass, can be minecraft:air)
+    --schem-axes <xyz>    the axes the bits, words and pages of a schematic run along (default yxz)
+    --bit-spacing <n>     the distance between bits in a schematic (default 2)
+    --word-spacing <n>    the distance between words in a schematic (default 2)
+    --page-spacing <n>    the distance between page banks in a schematic (default 4)
+    --no-tokens           don't print the final token dump (the debug prints go to stderr)
+    --no-lines            don't print each compiled line
+    -q, --quiet           same as --no-tokens --no-lines
+    -h, --help            prints this message";
+
+// parses the arguments (not including the binary's name)
+pub fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut args = args.peekable();
+    let command = match args.next().as_deref() {
+        Some("assemble" | "asm") => Command::Assemble,
+        Some("run") => Command::Run,
+        Some("disasm") => Command::Disasm,
+        Some("check") => Command::Check,
+        Some("circ") => Command::Circ,
+        Some("new") => Command::New,
+        Some("-h" | "--help") => return Err(String::new()),
+        Some(other) => return Err(format!("unknown command '{}'", other)),
+        None => return Err(String::from("no command given")),
+    };
+
+    let mut input = None;
+    let mut options = AssembleOptions::default();
+    let mut output = None;
+    let mut format = None;
+    let mut circ = None;
+    let mut roms = vec![];
+    let mut schematic = SchematicLayout::default();
+    let mut symbols = None;
+    let mut listing = None;
+    let mut write_symbols = None;
+    let mut ram_image = None;
+    let mut disc_image = None;
+    let mut save_disc = None;
+    let mut print_tokens = true;
+    let mut print_lines = true;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "-f" | "--format" => {
+                let name = args.next().ok_or_else(|| format!("'{}' expects a format", arg))?;
+                format = Some(Format::from_name(&name).ok_or_else(|| format!("unknown format '{}'", name))?);
+            },
+            "--symbols" => {
+                symbols = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "--write-symbols" => {
+                write_symbols = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "--listing" => {
+                listing = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "--ram-image" => {
+                ram_image = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "--disc-image" => {
+                disc_image = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "--save-disc" => {
+                save_disc = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "--circ" => {
+                circ = Some(args.next().ok_or_else(|| format!("'{}' expects a path", arg))?);
+            },
+            "--rom" => {
+                roms.push(args.next().ok_or_else(|| format!("'{}' expects a label or location", arg))?);
+            },
+            "--one-block" => {
+                schematic.one_block = args.next().ok_or_else(|| format!("'{}' expects a block id", arg))?;
+            },
+            "--zero-block" => {
+                schematic.zero_block = args.next().ok_or_else(|| format!("'{}' expects a block id", arg))?;
+            },
+            "--schem-axes" => {
+                schematic.set_axes(&args.next().ok_or_else(|| format!("'{}' expects axes like yxz", arg))?)?;
+            },
+            "--bit-spacing" | "--word-spacing" | "--page-spacing" => {
+                let spacing = args.next()
+                    .and_then(|spacing| spacing.parse::<usize>().ok())
+                    .filter(|spacing| (1..=MAX_SPACING).contains(spacing))
+                    .ok_or_else(|| format!("'{}' expects a number from 1 to {}", arg, MAX_SPACING))?;
+                match arg.as_str() {
+                    "--bit-spacing" => schematic.bit_spacing = spacing,
+                    "--word-spacing" => schematic.word_spacing = spacing,
+                    _ => schematic.page_spacing = spacing,
+                }
+            },
+            "--auto-page" => { options.auto_page = true; },
+            "--strict" => { options.strict = true; },
+            "--macro-depth" | "--max-expansion" => {
+                let limit = args.next()
+                    .and_then(|limit| limit.parse::<usize>().ok())
+                    .ok_or_else(|| format!("'{}' expects a number", arg))?;
+                if arg == "--macro-depth" { options.max_macro_depth = limit; } else { options.max_expanded_lines = limit; }
+            },
+            "-D" => {
+                let define = args.next().ok_or_else(|| format!("'{}' expects a name like NAME=value", arg))?;
+                options.defines.push(parse_define(&define)?);
+            },
+            flag if flag.starts_with("-D") => { options.defines.push(parse_define(&flag[2..])?); },
+            "--no-tokens" => { print_tokens = false; },
+            "--no-lines" => { print_lines = false; },
+            "-q" | "--quiet" => { print_tokens = false; print_lines = false; },
+            "-h" | "--help" => return Err(String::new()),
+            flag if flag.starts_with('-') => return Err(format!("unknown option '{}'", flag)),
+            path => {
+                if input.is_some() { return Err(format!("unexpected argument '{}'", path)); }
+                input = Some(path.to_string());
+            },
+        }
+    }
+
+    let input = input.ok_or_else(|| String::from("no input file given"))?;
+    Ok(Args { command, input, options, output, format, circ, roms, schematic, symbols, write_symbols, listing, ram_image, disc_image, save_disc, print_tokens, print_lines })
+}
+
+// NAME=value, or just NAME (which is 1)
+fn parse_define(define: &str) -> Result<(String, String), String> {
+    let (name, value) = define.split_once('=').unwrap_or((define, "1"));
+    if name.is_empty() || name.contains(char::is_whitespace) { return Err(format!("'-D {}' expects a name like NAME=value", define)); }
+    Ok((name.to_string(), value.to_string()))
+}
@@ -0,0 +1,94 @@
+use crate::isa::{operand_kinds, operand_shift, OperandKind, OP_CODES, REGISTERS};
+use crate::symbols::SymbolTable;
+
+// splits a word into its mnemonic and operand values (None if the op code isn't in OP_CODES)
+pub fn decode(word: u32) -> Option<(&'static str, Vec<u8>)> {
+    let op_code = (word >> 24) as u8;
+    let (_, num_args, offsets, name) = OP_CODES.iter().find(|(code, ..)| *code == op_code)?;
+    let values = (0..*num_args).map(|i| ((word >> operand_shift(i, offsets[i])) & 0xFF) as u8).collect();
+    Some((name, values))
+}
+
+// the word the assembler writes for the mnemonic and operand values
+pub fn encode(name: &str, values: &[u8]) -> Option<u32> {
+    let (op_code, _, offsets, _) = OP_CODES.iter().find(|op| op.3 == name)?;
+    Some(values.iter().enumerate().fold((*op_code as u32) << 24, |word, (i, value)| word | (*value as u32) << operand_shift(i, offsets[i])))
+}
+
+// turns pages of words back into assembly that assembles to the same words
+// without symbols, every jump target gets a generated header (like L0_12 for page 0, line 12)
+pub fn disassemble(pages: &[Vec<u32>], symbols: Option<&SymbolTable>) -> String {
+    let generated;
+    let symbols = match symbols {
+        Some(symbols) => symbols,
+        None => { generated = generate_labels(pages); &generated },
+    };
+
+    let mut text = String::new();
+    for (page_index, words) in pages.iter().enumerate() {
+        if page_index > 0 {
+            // page 0 is always main
+            let name = symbols.page_name(page_index).map_or_else(|| format!("page_{}", page_index), |name| name.to_string());
+            text.push_str(&format!("\n!page {}\n", name));
+        }
+        for (line, word) in words.iter().enumerate() {
+            for header in symbols.headers_at(page_index, line) {
+                text.push_str(&format!("!header {}\n", header));
+            }
+            let (instruction, note) = match decode(*word) {
+                // kept as the raw word so it reassembles the same (it's probably data from a !byte/!string/...)
+                Some((name, values)) if encode(name, &values) != Some(*word) => {
+                    (format!("!word 0x{:08x}", word), format!(" (looks like `{}`, but has bits outside its operands)", instruction_text(*word, page_index, symbols)))
+                },
+                Some(_) => (instruction_text(*word, page_index, symbols), String::new()),
+                None => (format!("!word 0x{:08x}", word), format!(" (unknown op code {:02x})", word >> 24)),
+            };
+            text.push_str(&format!("    {:<28}; {}:{:<3} {:08x}{}\n", instruction, page_index, line, word, note));
+        }
+        // headers pointing just past the last instruction
+        for header in symbols.headers_at(page_index, words.len()) {
+            text.push_str(&format!("!header {}\n", header));
+        }
+    } text
+}
+
+// a single word as assembly, with register, header and page names filled in from the symbols
+pub fn instruction_text(word: u32, page_index: usize, symbols: &SymbolTable) -> String {
+    let Some((name, values)) = decode(word) else { return format!("{:08x}", word) };
+    let operands = values.iter().zip(operand_kinds(name)).map(|(value, kind)| {
+        operand_text(*value, *kind, name, &values, page_index, symbols)
+    }).collect::<Vec<String>>();
+    format!("{} {}", name, operands.join(" ")).trim_end().to_string()
+}
+
+fn operand_text(value: u8, kind: OperandKind, name: &str, values: &[u8], page_index: usize, symbols: &SymbolTable) -> String {
+    match kind {
+        OperandKind::Register if (value as usize) < REGISTERS.len() => REGISTERS[value as usize].to_string(),
+        OperandKind::Line => {
+            // jumps stay on the page they're in (as far as we can tell), Goto names its page
+            let page = if name == "Goto" { values.get(1).map_or(page_index, |page| *page as usize) } else { page_index };
+            symbols.headers_at(page, value as usize).next().map_or_else(|| value.to_string(), |header| header.to_string())
+        },
+        OperandKind::Page => symbols.page_name(value as usize).map_or_else(|| value.to_string(), |page| page.to_string()),
+        _ => value.to_string(),
+    }
+}
+
+// headers for every spot a Jmp/Jiz/Jnz/Goto lands on
+fn generate_labels(pages: &[Vec<u32>]) -> SymbolTable {
+    let mut symbols = SymbolTable::default();
+    for (page_index, words) in pages.iter().enumerate() {
+        for word in words {
+            let target = match decode(*word) {
+                Some(("Jmp" | "Jiz" | "Jnz", values)) => (page_index, values[0] as usize),
+                Some(("Goto", values)) => (values[1] as usize, values[0] as usize),
+                _ => continue,
+            };
+            let (page, line) = target;
+            if pages.get(page).is_none_or(|words| line > words.len()) { continue; }
+            if symbols.headers_at(page, line).next().is_none() {
+                symbols.headers.push((format!("L{}_{}", page, line), line, page));
+            }
+        }
+    } symbols
+}
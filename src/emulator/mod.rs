@@ -0,0 +1,281 @@
+use std::sync::{Arc, Mutex};
+
+use crate::assembler::Program;
+
+pub mod terminal;
+
+pub const DISPLAY_SIZE: usize = 32;
+
+// the full state of the computer
+pub struct Machine {
+    pub program_bytes: Vec<Vec<u32>>,
+
+    // memory
+    pub registers: [u8; 256],
+    pub ram: [u8; 256],
+    pub stack: [u8; 64],
+    pub disc: [u8; 256],
+    // shared so a frontend can draw it/feed it keys from another thread
+    pub display: Arc<Mutex<[u8; DISPLAY_SIZE * DISPLAY_SIZE]>>,
+    pub io_in: Arc<Mutex<u8>>,
+
+    // dedicated registers
+    pub program_counter: u16,
+    pub alu_left: u8,
+    pub alu_right: u8,
+    pub alu_out: u8,
+    pub pointer_reg: u8,
+    pub overflow_flag: bool,
+    pub condition_flag: bool,
+    pub next_page_reg: u8,
+    pub x_coord_reg: u8,
+    pub y_coord_reg: u8,
+    pub color_reg: u8,
+
+    pub io_in_flag: bool,
+    pub io_out_flag: bool,
+    pub io_out: u8,
+
+    pub cycle: u128,  // just for debug stuff ig
+    pub killed: bool,
+}
+
+impl Machine {
+    // starts with the ram and disc the program sets with !ram/!disc
+    pub fn new(program: &Program) -> Machine {
+        let mut machine = Machine::from_bytes(program.page_words());
+        if !program.ram.is_empty() { machine.ram.copy_from_slice(&program.ram); }
+        if !program.disc.is_empty() { machine.disc.copy_from_slice(&program.disc); }
+        machine
+    }
+
+    pub fn from_bytes(program_bytes: Vec<Vec<u32>>) -> Machine {
+        Machine {
+            program_bytes,
+            registers: [0u8; 256],
+            ram: [0u8; 256],
+            stack: [0u8; 64],
+            disc: [0u8; 256],
+            display: Arc::new(Mutex::new([0u8; DISPLAY_SIZE * DISPLAY_SIZE])),
+            io_in: Arc::new(Mutex::new(0u8)),
+            program_counter: 0,
+            alu_left: 0,
+            alu_right: 0,
+            alu_out: 0,
+            pointer_reg: 0,
+            overflow_flag: false,
+            condition_flag: false,
+            next_page_reg: 0,
+            x_coord_reg: 0,
+            y_coord_reg: 0,
+            color_reg: 0,
+            io_in_flag: false,
+            io_out_flag: false,
+            io_out: 0,
+            cycle: 0,
+            killed: false,
+        }
+    }
+
+    // runs until Kill, or until max_cycles more cycles have run
+    pub fn run(&mut self, max_cycles: Option<u128>) {
+        let start = self.cycle;
+        while !self.killed && max_cycles.is_none_or(|max| self.cycle - start < max) {
+            self.step();
+        }
+    }
+
+    // runs a single instruction
+    pub fn step(&mut self) {
+        if self.killed { return; }
+        self.cycle += 1;
+
+        let instruction = self.program_bytes[(self.program_counter >> 8) as usize][(self.program_counter & 0xFF) as usize];
+        let op_code = (instruction >> 24) as u8;
+        let reg_or_add = ((instruction >> 16) & 0xFF) as u8;
+        let immediate = ((instruction >> 8) & 0xFF) as u8;
+        let immediate_2 = (instruction & 0xFF) as u8;
+        let mut jumped = false;
+        match op_code {
+            0b000_00001 => { self.x_coord_reg = self.alu_out; },  // "SetDspInX"
+            0b000_00010 => { self.y_coord_reg = self.alu_out; },  // "SetDspInY"
+            0b000_00011 => { self.color_reg = self.alu_out; },  // "SetDspInCol"
+            0b000_00100 => { self.display.lock().unwrap()[immediate as usize + immediate_2 as usize * DISPLAY_SIZE] = self.color_reg; },  // "Plot"
+            0b000_00101 => { self.killed = true; return; },  // "Kill"
+            0b000_00110 => { self.pointer_reg = self.alu_out; },  // "SetPtr"
+            0b000_00111 => { self.alu_left = (self.program_counter & 0xFF) as u8; },  // "PgcL"
+            0b000_01000 => { self.alu_right = (self.program_counter & 0xFF) as u8; },  // "PgcR"
+            0b000_01001 => { self.display.lock().unwrap()[self.x_coord_reg as usize + self.y_coord_reg as usize * DISPLAY_SIZE] = self.color_reg; },  // "Plt"
+            0b000_01010 => { self.next_page_reg = immediate_2; },  // "SetPage"
+            0b000_01011 => { self.run_lu(op_code, &mut jumped, immediate, immediate_2, reg_or_add); },  // "Goto"
+            0b000_01100 => { self.run_lu(op_code, &mut jumped, immediate, immediate_2, reg_or_add); },  // "GotoReg"
+            0b000_01101 => { self.next_page_reg = self.registers[reg_or_add as usize]; },  // "SetPageReg"
+            0b000_01110 => { self.registers[reg_or_add as usize] = self.io_in_flag as u8; },  // "ReadInFlg"
+            0b000_01111 => { self.registers[reg_or_add as usize] = *self.io_in.lock().unwrap() },  // "ReadIn"
+            0b000_10000 => { self.io_in_flag = false; },  // "ResetInFlg"
+            0b000_10001 => { self.io_out_flag = self.alu_out > 0; },  // "SetOutFlg"
+            0b000_10010 => { self.io_out = self.alu_out; },  // "SetOut"
+            0b000_10011 => { self.alu_left = self.rom_byte(reg_or_add); },  // "RomL"
+            0b000_10100 => { self.alu_right = self.rom_byte(reg_or_add); },  // "RomR"
+            0b001_00000..=0b001_01011 | 0b010_00000..=0b010_00111 => {
+                // "Add" through "LdiR", and "Eq" through "RsetO"
+                run_alu(op_code, immediate, &mut self.alu_left, &mut self.alu_right, &mut self.alu_out, &mut self.overflow_flag, &mut self.condition_flag);
+            },
+            0b011_00000 => { self.alu_left = self.registers[reg_or_add as usize]; },  // "LodL"
+            0b011_00001 => { self.alu_right = self.registers[reg_or_add as usize]; },  // "LodR"
+            0b011_00010 => { self.registers[reg_or_add as usize] = self.alu_out; },  // "WrtO"
+            0b011_00011 => { self.alu_left = self.pointer_reg; },  // "PtrL"
+            0b011_00100 => { self.alu_right = self.pointer_reg; },  // "PtrR"
+            0b011_00101 => { self.pointer_reg = self.alu_out; },  // "PtrO"
+            0b011_00110 => { self.registers[reg_or_add as usize] = immediate; },  // "Ldi"
+            0b100_00000 => { self.alu_left = self.ram[reg_or_add as usize]; },  // "RLodL"
+            0b100_00001 => { self.alu_right = self.ram[reg_or_add as usize]; },  // "RLodR"
+            0b100_00010 => { self.ram[reg_or_add as usize] = self.alu_out; },  // "RWrtO"
+            0b100_00011 => { self.alu_left = self.ram[self.pointer_reg as usize]; },  // "RPtrL"
+            0b100_00100 => { self.alu_right = self.ram[self.pointer_reg as usize]; },  // "RPtrR"
+            0b100_00101 => { self.ram[self.pointer_reg as usize] = self.alu_out; },  // "RPtrO"
+            0b100_00110 => { self.ram[reg_or_add as usize] = immediate; },  // "RLdi"
+            0b101_00000 => { self.alu_left = self.disc[reg_or_add as usize]; },  // "DLodL"
+            0b101_00001 => { self.alu_right = self.disc[reg_or_add as usize]; },  // "DLodR"
+            0b101_00010 => { self.disc[reg_or_add as usize] = self.alu_out; },  // "DWrtO"
+            0b101_00011 => { self.alu_left = self.disc[self.pointer_reg as usize]; },  // "DPtrL"
+            0b101_00100 => { self.alu_right = self.disc[self.pointer_reg as usize]; },  // "DPtrR"
+            0b101_00101 => { self.disc[self.pointer_reg as usize] = self.alu_out; },  // "DPtrO"
+            0b101_00110 => { self.disc[reg_or_add as usize] = immediate; },  // "DLdi"
+            0b110_00000..=0b110_00101 => { self.run_lu(op_code, &mut jumped, immediate, immediate_2, reg_or_add) },  // "Jmp" through "JnzR"
+            0b111_00000 => { let stack = &mut self.stack; for i in 0..stack.len() - 1 { stack[i] = stack[i + 1] } stack[stack.len() - 1] = 0; },  // "Pop"
+            0b111_00001 => { self.alu_left = self.stack[0]; },  // "TopL"
+            0b111_00010 => { self.alu_right = self.stack[0]; },  // "TopR"
+            0b111_00011 => { let stack = &mut self.stack; for i in (0..stack.len() - 1).rev() { stack[i + 1] = stack[i] } stack[0] = self.alu_out; },  // "PshO"
+            0b111_00100 => { let stack = &mut self.stack; for i in (0..stack.len() - 1).rev() { stack[i + 1] = stack[i] } stack[0] = immediate; },  // "PshCon"
+            _ => {}
+        }
+        if !jumped {
+            self.program_counter += 1;
+        }
+    }
+
+    // the low byte of the word at the pointer on the given page, for reading the tables placed with !byte/!string/...
+    // (0 past the end of the program)
+    fn rom_byte(&self, page: u8) -> u8 {
+        let word = self.program_bytes.get(page as usize).and_then(|words| words.get(self.pointer_reg as usize));
+        word.map_or(0, |word| (word & 0xFF) as u8)
+    }
+
+    fn run_lu(&mut self, op_code: u8, jumped: &mut bool, immediate: u8, immediate_2: u8, reg_or_add: u8) {
+        let condition_flag = self.condition_flag;
+        let next_page = (self.next_page_reg as u16) << 8;
+        match op_code {
+            0b000_01011 => {
+                self.next_page_reg = immediate_2;
+                *jumped = true;
+                self.program_counter = immediate as u16 | ((immediate_2 as u16) << 8);
+            },  // "Goto"
+            0b000_01100 => {
+                *jumped = true;
+                self.program_counter = (self.registers[reg_or_add as usize]) as u16 | next_page;
+            },  // "GotoReg"
+            0b110_00000 => { *jumped = true; self.program_counter = reg_or_add as u16 | next_page; },  // "Jmp"
+            0b110_00001 if condition_flag => { *jumped = true; self.program_counter = reg_or_add as u16 | next_page; },  // "Jiz"
+            0b110_00010 if !condition_flag => { *jumped = true; self.program_counter = reg_or_add as u16 | next_page; },  // "Jnz"
+            0b110_00011 => { *jumped = true; self.program_counter = (self.registers[reg_or_add as usize]) as u16 | next_page; },  // "JmpR"
+            0b110_00100 if condition_flag => { *jumped = true; self.program_counter = (self.registers[reg_or_add as usize]) as u16 | next_page; },  // "JizR"
+            0b110_00101 if !condition_flag => { *jumped = true; self.program_counter = (self.registers[reg_or_add as usize]) as u16 | next_page; },  // "JnzR"
+            _ => {}
+        }
+    }
+}
+
+// the alu is 8 bits wide like the circuit's, so Add/Sub/Inc/Dec wrap around rather than panicking (the overflow flag is how
+// a program finds out, and it stays set until RsetO clears it)
+fn run_alu(op_code: u8, immediate: u8, left: &mut u8, right: &mut u8, out: &mut u8, overflow_flag: &mut bool, condition_flag: &mut bool) {
+    match op_code {
+        0b001_00000 => {
+            if left.checked_add(*right).is_none() { *overflow_flag = true; }
+            *out = left.wrapping_add(*right);
+        },  // "Add"
+        0b001_00001 => { *out = left.wrapping_sub(*right); },  // "Sub"
+        0b001_00010 => {
+            if left.checked_add(1).is_none() { *overflow_flag = true; }
+            *out = left.wrapping_add(1);
+        },  // "Inc"
+        0b001_00011 => { *out = left.wrapping_sub(1); },  // "Dec"
+        0b001_00100 => { *out = *left; },  // "ThruL"
+        0b001_00101 => { *out = *right; },  // "ThruR"
+        0b001_00110 => { *out = *left & *right; },  // "And"
+        0b001_00111 => { *out = *left | *right; },  // "Or"
+        0b001_01000 => {
+            // the bit shifted out of the top is the overflow (checked_shl only fails for shifts of 8 or more)
+            if *left & 0x80 != 0 { *overflow_flag = true; }
+            *out = *left << 1;
+        },  // "ShftL"
+        0b001_01001 => { *out = *left >> 1; },  // "ShftR"
+        0b001_01010 => { *left = immediate; },  // "LdiL"
+        0b001_01011 => { *right = immediate; },  // "LdiR"
+        0b010_00000 => { *condition_flag = *left == *right; },  // "Eq"
+        0b010_00001 => { *condition_flag = *left < *right; },  // "Less"
+        0b010_00010 => { *condition_flag = *left > *right; },  // "Grtr"
+        0b010_00011 => { *condition_flag = *overflow_flag; },  // "OvrFlow"
+        0b010_00100 => { *condition_flag = true; },  // "SetC"
+        0b010_00101 => { *condition_flag = false; },  // "RsetC"
+        0b010_00110 => { *condition_flag = *out == 0; },  // "Zero"
+        0b010_00111 => { *overflow_flag = false; },  // "RsetO"
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Machine;
+    use crate::assembler::{assemble_with, AssembleOptions};
+
+    // runs the script until Kill, starting with the given values in rda, rdb, ...
+    fn run(source: &str, registers: &[u8], overflow_flag: bool) -> Machine {
+        let program = assemble_with("test.mca", source, &AssembleOptions::default()).unwrap();
+        let mut machine = Machine::new(&program);
+        machine.registers[..registers.len()].copy_from_slice(registers);
+        machine.overflow_flag = overflow_flag;
+        machine.run(Some(1000));
+        assert!(machine.killed, "the script never reached Kill");
+        machine
+    }
+
+    #[test]
+    fn alu_wraps() {
+        let machine = run("LdiL 255\nLdiR 2\nAdd\nWrtO rda\nLdiL 1\nSub\nWrtO rdb\nLdiL 0\nDec\nWrtO rdc\nLdiL 255\nInc\nWrtO rdd\nKill", &[], false);
+        assert_eq!(machine.registers[..4], [1, 255, 255, 0]);
+        assert!(machine.overflow_flag);
+    }
+
+    #[test]
+    fn overflow_stays_until_rseto() {
+        let machine = run("LdiL 255\nLdiR 1\nAdd\nLdiL 1\nAdd\nKill", &[], false);
+        assert!(machine.overflow_flag);
+        let machine = run("LdiL 255\nLdiR 1\nAdd\nRsetO\nOvrFlow\nKill", &[], false);
+        assert!(!machine.overflow_flag && !machine.condition_flag);
+    }
+
+    #[test]
+    fn shift_left_overflows_on_the_top_bit() {
+        let machine = run("LdiL 129\nShftL\nOvrFlow\nKill", &[], false);
+        assert_eq!((machine.alu_out, machine.condition_flag), (2, true));
+        let machine = run("LdiL 64\nShftL\nOvrFlow\nKill", &[], false);
+        assert_eq!((machine.alu_out, machine.condition_flag), (128, false));
+    }
+
+    #[test]
+    fn add16_carries() {
+        // 0x01ff + 0x0001, and 0x0001 + 0x0001 with the overflow flag already set by something earlier
+        assert_eq!(run("!use std\nadd16 rda rdb rdc rdd\nKill", &[0xff, 0x01, 0x01, 0x00], false).registers[..2], [0x00, 0x02]);
+        assert_eq!(run("!use std\nadd16 rda rdb rdc rdd\nKill", &[0x01, 0x00, 0x01, 0x00], true).registers[..2], [0x02, 0x00]);
+        assert_eq!(run("!use std\nadd16 rda rdb rdc rdd\nKill", &[0xff, 0xff, 0x01, 0x00], false).registers[..2], [0x00, 0x00]);
+    }
+
+    #[test]
+    fn sub16_borrows() {
+        // 0x0200 - 0x0001, 0x0000 - 0x0001 and 0x1234 - 0x0034
+        assert_eq!(run("!use std\nsub16 rda rdb rdc rdd\nKill", &[0x00, 0x02, 0x01, 0x00], false).registers[..2], [0xff, 0x01]);
+        assert_eq!(run("!use std\nsub16 rda rdb rdc rdd\nKill", &[0x00, 0x00, 0x01, 0x00], false).registers[..2], [0xff, 0xff]);
+        assert_eq!(run("!use std\nsub16 rda rdb rdc rdd\nKill", &[0x34, 0x12, 0x34, 0x00], false).registers[..2], [0x00, 0x12]);
+    }
+}
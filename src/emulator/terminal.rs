@@ -0,0 +1,80 @@
+use std::io::{Read, Write};
+
+use super::{Machine, DISPLAY_SIZE};
+
+// runs the machine until it's killed (or capital Q is pressed), drawing the display to the terminal
+pub fn run_terminal(machine: &mut Machine) {
+    let display_clone = machine.display.clone();
+    let io_in_ref = machine.io_in.clone();
+
+    crossterm::terminal::enable_raw_mode().unwrap();
+    let (sender, receiver) = std::sync::mpsc::channel::<()>();
+    let (sender_2, receiver_2) = std::sync::mpsc::channel::<()>();
+    let thread_handle = std::thread::spawn(move || {
+        print!("\x1b[?25l");
+        let display = display_clone;
+        let mut buf = std::io::BufWriter::new(std::io::stdout());
+        println!("{}", "\n".repeat(50));
+        loop {
+            if receiver.try_recv().is_ok() { break; }
+            let mut text = String::from("\x1B[H");
+            for y in 0..DISPLAY_SIZE {
+                for x in 0..DISPLAY_SIZE {
+                    let display_locked = display.lock().unwrap();
+                    let r = ((display_locked[x + y * DISPLAY_SIZE] >> 4) & 0b11) * 85;
+                    let g = ((display_locked[x + y * DISPLAY_SIZE] >> 2) & 0b11) * 85;
+                    let b = (display_locked[x + y * DISPLAY_SIZE] & 0b11) * 85;
+                    text.push_str(&format!("\x1b[{};{}H\x1B[48;2;{};{};{}m   \x1B[0m", y + 1, x * 3 + 1, r, g, b));
+                }
+                text.push('\n');
+            }
+            writeln!(&mut buf, "{}", text).unwrap();
+        }
+        print!("\x1b[?25h");
+    });
+
+    let (kill_send, kill_recv) = std::sync::mpsc::channel::<()>();
+    let _thread_handle_2 = std::thread::spawn(move || {
+        let mut stdin = std::io::stdin();
+        loop {
+            if receiver_2.try_recv().is_ok() { break; }
+            let mut local_buffer = [0; 12];
+            let result = stdin.read(&mut local_buffer);
+            if let Ok(_n) = result {
+                //println!("Keycode: {:x}, buffer: {:x?}", n, local_buffer);
+                if local_buffer[0] == 0x51 {  // safety release to prevent a runaway.....
+                    // capital Q
+                    crossterm::terminal::disable_raw_mode().unwrap();
+                    kill_send.send(()).unwrap();
+                    return;
+                }
+                *io_in_ref.lock().unwrap() = local_buffer[0];
+            }
+        }
+    });
+
+    let start_cycle = machine.cycle;
+    let time_start = std::time::Instant::now();
+
+    while !machine.killed {
+        //println!("PC: {}, Page: {}, ALU Left: {}, ALU Right: {}, ALU Out: {}, Overflow Flag: {}, Condition Flag: {}", program_counter & 0xFF, ((program_counter & 0xFF00) >> 8), alu_left, alu_right, alu_out, overflow_flag, condition_flag);
+        //std::thread::sleep(std::time::Duration::from_secs_f32(0.00025));
+        if kill_recv.try_recv().is_ok() { break; }  // force quite
+        machine.step();
+    }
+    let cycles = machine.cycle - start_cycle;
+    let end = time_start.elapsed().as_secs_f64() / cycles as f64;
+
+    let _ = sender_2.send(());  // not going to join the thread since std-in stalls everything until an input is present
+    sender.send(()).unwrap();
+    thread_handle.join().unwrap();
+    crossterm::terminal::disable_raw_mode().unwrap();
+
+    println!("PC: {}, ALU Left: {}, ALU Right: {}, ALU Out: {}, Overflow Flag: {}, Condition Flag: {}", machine.program_counter, machine.alu_left, machine.alu_right, machine.alu_out, machine.overflow_flag, machine.condition_flag);
+    println!("Ram: {:?}", &machine.ram);
+    println!("First 10 registers: {:?}", &machine.registers[..10]);
+    println!("First 20 of stack: {:?}", &machine.stack[..20]);
+    std::thread::sleep(std::time::Duration::from_secs_f32(0.1));
+
+    println!("Average Cycle Time: {} seconds, which is about {} operations per second over {} cycles", end, 1.0 / end, cycles);
+}
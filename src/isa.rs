@@ -0,0 +1,127 @@
+// op_code/op_type combined, num args, name
+pub static OP_CODES: &[(u8, usize, [usize; 3], &str)] = &[
+    (0b000_00000, 0, [0, 0, 0], "Nop"),
+    (0b000_00001, 0, [0, 0, 0], "SetDspInX"),
+    (0b000_00010, 0, [0, 0, 0], "SetDspInY"),
+    (0b000_00011, 0, [0, 0, 0], "SetDspInCol"),
+    (0b000_00100, 2, [1, 0, 0], "Plot"),
+    (0b000_00101, 0, [0, 0, 0], "Kill"),
+    (0b000_00110, 0, [0, 0, 0], "SetPtr"),
+    (0b000_00111, 0, [0, 0, 0], "PgcL"),
+    (0b000_01000, 0, [0, 0, 0], "PgcR"),
+    (0b000_01001, 0, [0, 0, 0], "Plt"),
+    (0b000_01010, 1, [1, 0, 0], "SetPage"),
+    (0b000_01011, 2, [1, 1, 0], "Goto"),
+    (0b000_01100, 2, [0, 1, 0], "GotoReg"),
+    (0b000_01101, 1, [0, 0, 0], "SetPageReg"),
+    (0b000_01110, 1, [0, 0, 0], "ReadInFlg"),
+    (0b000_01111, 1, [0, 0, 0], "ReadIn"),
+    (0b000_10000, 0, [0, 0, 0], "ResetInFlg"),
+    (0b000_10001, 0, [0, 0, 0], "SetOutFlg"),
+    (0b000_10010, 0, [0, 0, 0], "SetOut"),
+    (0b000_10011, 1, [0, 0, 0], "RomL"),
+    (0b000_10100, 1, [0, 0, 0], "RomR"),
+    (0b001_00000, 0, [0, 0, 0], "Add"),
+    (0b001_00001, 0, [0, 0, 0], "Sub"),
+    (0b001_00010, 0, [0, 0, 0], "Inc"),
+    (0b001_00011, 0, [0, 0, 0], "Dec"),
+    (0b001_00100, 0, [0, 0, 0], "ThruL"),
+    (0b001_00101, 0, [0, 0, 0], "ThruR"),
+    (0b001_00110, 0, [0, 0, 0], "And"),
+    (0b001_00111, 0, [0, 0, 0], "Or"),
+    (0b001_01000, 0, [0, 0, 0], "ShftL"),
+    (0b001_01001, 0, [0, 0, 0], "ShftR"),
+    (0b001_01010, 1, [1, 0, 0], "LdiL"),
+    (0b001_01011, 1, [1, 0, 0], "LdiR"),
+    (0b010_00000, 0, [0, 0, 0], "Eq"),
+    (0b010_00001, 0, [0, 0, 0], "Less"),
+    (0b010_00010, 0, [0, 0, 0], "Grtr"),
+    (0b010_00011, 0, [0, 0, 0], "OvrFlow"),
+    (0b010_00100, 0, [0, 0, 0], "SetC"),
+    (0b010_00101, 0, [0, 0, 0], "RsetC"),
+    (0b010_00110, 0, [0, 0, 0], "Zero"),
+    (0b010_00111, 0, [0, 0, 0], "RsetO"),
+    (0b011_00000, 1, [0, 0, 0], "LodL"),
+    (0b011_00001, 1, [0, 0, 0], "LodR"),
+    (0b011_00010, 1, [0, 0, 0], "WrtO"),
+    (0b011_00011, 0, [0, 0, 0], "PtrL"),
+    (0b011_00100, 0, [0, 0, 0], "PtrR"),
+    (0b011_00101, 0, [0, 0, 0], "PtrO"),
+    (0b011_00110, 2, [0, 0, 0], "Ldi"),
+    (0b100_00000, 1, [0, 0, 0], "RLodL"),
+    (0b100_00001, 1, [0, 0, 0], "RLodR"),
+    (0b100_00010, 1, [0, 0, 0], "RWrtO"),
+    (0b100_00011, 0, [0, 0, 0], "RPtrL"),
+    (0b100_00100, 0, [0, 0, 0], "RPtrR"),
+    (0b100_00101, 0, [0, 0, 0], "RPtrO"),
+    (0b100_00110, 2, [0, 0, 0], "RLdi"),
+    (0b101_00000, 1, [0, 0, 0], "DLodL"),
+    (0b101_00001, 1, [0, 0, 0], "DLodR"),
+    (0b101_00010, 1, [0, 0, 0], "DWrtO"),
+    (0b101_00011, 0, [0, 0, 0], "DPtrL"),
+    (0b101_00100, 0, [0, 0, 0], "DPtrR"),
+    (0b101_00101, 0, [0, 0, 0], "DPtrO"),
+    (0b101_00110, 2, [0, 0, 0], "DLdi"),
+    (0b110_00000, 1, [0, 0, 0], "Jmp"),
+    (0b110_00001, 1, [0, 0, 0], "Jiz"),
+    (0b110_00010, 1, [0, 0, 0], "Jnz"),
+    (0b110_00011, 1, [0, 0, 0], "JmpR"),
+    (0b110_00100, 1, [0, 0, 0], "JizR"),
+    (0b110_00101, 1, [0, 0, 0], "JnzR"),
+    (0b111_00000, 0, [0, 0, 0], "Pop"),
+    (0b111_00001, 0, [0, 0, 0], "TopL"),
+    (0b111_00010, 0, [0, 0, 0], "TopR"),
+    (0b111_00011, 0, [0, 0, 0], "PshO"),
+    (0b111_00100, 1, [1, 0, 0],	"PshCon"),
+];
+
+// instructions only the emulator runs so far, since the circuit's control ROM doesn't decode them yet
+pub static EMULATOR_ONLY: &[&str] = &["RomL", "RomR"];
+pub static REGISTERS: &[&str] = &[
+    "rda",
+    "rdb",
+    "rdc",
+    "rdd",
+    "rde",
+    "rdf",
+    "rdg",
+    "rdh",
+    "rdi",
+    "rdj",
+    "rdk",
+    "rdl",
+    "rdm",
+    "rdn",
+    "rdo",
+    "rdp",
+];
+
+// every page is its own 256 instruction bank, addressed by the low byte of the program counter
+pub const PAGE_SIZE: usize = 256;
+
+// what an instruction's operands mean, which decides how the disassembler prints them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    Register,  // an index into the registers (printed as rda through rdp)
+    Line,      // a line within a page (usually a header)
+    Page,      // a page index
+    Value,     // anything else (immediates, ram/disc addresses)
+}
+
+pub fn operand_kinds(name: &str) -> &'static [OperandKind] {
+    use OperandKind::*;
+    match name {
+        "LodL" | "LodR" | "WrtO" | "SetPageReg" | "ReadInFlg" | "ReadIn" | "JmpR" | "JizR" | "JnzR" => &[Register],
+        "Ldi" => &[Register, Value],
+        "GotoReg" => &[Register, Page],
+        "Jmp" | "Jiz" | "Jnz" => &[Line],
+        "Goto" => &[Line, Page],
+        "SetPage" | "RomL" | "RomR" => &[Page],
+        _ => &[Value, Value, Value],
+    }
+}
+
+// how far the operand at the given index is shifted within the 32 bit word, given its entry in OP_CODES' offsets
+pub fn operand_shift(index: usize, offset: usize) -> usize {
+    24 - 8 * (index + 1 + offset)
+}
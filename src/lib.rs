@@ -0,0 +1,16 @@
+// the assembler and emulator for the custom 8-bit ISA
+// the binary (main.rs) is just a thin command-line wrapper around these
+#![allow(non_snake_case)]  // the crate keeps the McAssembler name
+
+pub mod isa;
+pub mod assembler;
+pub mod disasm;
+pub mod emulator;
+pub mod listing;
+pub mod logisim;
+pub mod output;
+pub mod schematic;
+pub mod symbols;
+
+pub use assembler::{assemble, assemble_named, assemble_with, AssembleError, AssembleOptions, Program, Page};
+pub use emulator::Machine;
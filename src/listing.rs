@@ -0,0 +1,92 @@
+use crate::assembler::Program;
+use crate::disasm::instruction_text;
+use crate::symbols::SymbolTable;
+
+// a listing of the program for debugging the circuit, with a line for every word:
+//     0:003     66000500  Ldi rda 5                 main.mca:7  Ldi rda 5
+// (page:address, the word, the instruction it decodes to, then where it came from and the line itself, each column as wide as its longest entry)
+// headers show up as labels above the word they point at, and words spliced in by a macro
+// show the line from the macro's body followed by the macro call(s) that brought it in
+pub fn write_listing(program: &Program) -> String {
+    let symbols = SymbolTable::from_program(program);
+    // lining up the source lines, which start after the longest file:line
+    let span_width = program.pages.iter()
+        .flat_map(|page| page.origins.iter().map(|origin| origin.span.to_string().len()))
+        .max().unwrap_or_default();
+    // and the spans after the longest instruction, so a long macro label can't run into them
+    let instruction_width = program.pages.iter().enumerate()
+        .flat_map(|(page_index, page)| (0..page.words.len()).map(move |line| (page_index, line)))
+        .map(|(page_index, line)| listed_instruction(program, &symbols, page_index, line).len())
+        .max().unwrap_or_default();
+    let mut text = String::new();
+    for (page_index, page) in program.pages.iter().enumerate() {
+        if page_index > 0 { text.push('\n'); }
+        text.push_str(&format!("; page {} ({}), {} words\n", page_index, page.name, page.words.len()));
+        for (line, word) in page.words.iter().enumerate() {
+            for header in symbols.headers_at(page_index, line) {
+                text.push_str(&format!("{}:\n", header));
+            }
+            let location = format!("{}:{:03}", page_index, line);
+            let instruction = listed_instruction(program, &symbols, page_index, line);
+            let mut entry = format!("{:<8}  {:08x}  {:<width$}  ", location, word, instruction, width = instruction_width);
+            if let Some(origin) = page.origins.get(line) {
+                let source = program.source_line(&origin.span).unwrap_or_default().trim();
+                entry.push_str(&format!("{:<width$}  {}", origin.span.to_string(), source, width = span_width));
+                for expansion in origin.expansions.iter() {
+                    entry.push_str(&format!("  ({})", expansion));
+                }
+            }
+            text.push_str(entry.trim_end());
+            text.push('\n');
+        }
+        for header in symbols.headers_at(page_index, page.words.len()) {
+            text.push_str(&format!("{}:\n", header));
+        }
+    }
+    // where the `!var`/`!dvar` allocator put everything
+    for (memory, on_disc) in [("ram", false), ("disc", true)] {
+        let variables = program.variables.iter().filter(|variable| variable.3 == on_disc).collect::<Vec<_>>();
+        if variables.is_empty() { continue; }
+        let used = variables.iter().map(|variable| variable.2).sum::<usize>();
+        let name_width = variables.iter().map(|variable| variable.0.len()).max().unwrap_or_default();
+        text.push_str(&format!("\n; {}, {} of 256 bytes allocated\n", memory, used));
+        for (name, address, size, _) in variables {
+            let range = if *size == 1 { format!("{:03}", address) } else { format!("{:03}-{:03}", address, address + size - 1) };
+            text.push_str(&format!("{:<8}  {:<name_width$}  {} byte{}\n", range, name, size, if *size == 1 { "" } else { "s" }));
+        }
+    } text
+}
+
+// the instruction a word is listed as
+fn listed_instruction(program: &Program, symbols: &SymbolTable, page_index: usize, line: usize) -> String {
+    let page = &program.pages[page_index];
+    let word = page.words[line];
+    match page.instructions.get(line) {
+        // data placed with !byte/!string/... shows as the value, rather than whatever instruction it happens to look like
+        Some(tokens) if tokens[0] == "!byte" => format!("!byte {}", word),
+        Some(tokens) if tokens[0] == "!word" => format!("!word 0x{:08x}", word),
+        // a Jmp to a header on another page (once SetPage has pointed the page register at it) lands on that page's line,
+        // so it's named from that page rather than whatever header the current page has at the same line
+        Some(tokens) if ["Jmp", "Jiz", "Jnz"].contains(&tokens[0].as_str()) => match &page.targets[line] {
+            Some((header, _, target_page)) if *target_page != page_index => format!("{} {}::{}", tokens[0], program.pages[*target_page].name, header),
+            _ => instruction_text(word, page_index, symbols),
+        },
+        _ => instruction_text(word, page_index, symbols),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::write_listing;
+    use crate::assembler::{assemble_with, AssembleOptions};
+
+    #[test]
+    fn long_instructions_keep_a_gap_before_the_span() {
+        let source = "!macro fill\n  !header %%a_rather_long_loop_label\n  Jiz %%a_rather_long_loop_label\n!end\nfill\nKill";
+        let program = assemble_with("test.mca", source, &AssembleOptions::default()).unwrap();
+        let listing = write_listing(&program);
+        assert!(listing.contains("Jiz fill~1~a_rather_long_loop_label  test.mca:3"), "{}", listing);
+        assert!(listing.contains(&format!("{:<35}  test.mca:6", "Kill")), "{}", listing);
+    }
+}
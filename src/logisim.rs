@@ -0,0 +1,144 @@
+use crate::assembler::Program;
+use crate::isa::PAGE_SIZE;
+use crate::output::logisim_words;
+
+// a ROM component found in a .circ file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rom {
+    pub label: Option<String>,
+    pub loc: String,  // like "(380,1260)", for telling apart ROMs without labels
+    pub addr_width: usize,
+    pub data_width: usize,
+    pub range: std::ops::Range<usize>,  // the byte range of the whole <comp ...>...</comp> element
+}
+
+impl Rom {
+    // how many words the ROM holds
+    pub fn capacity(&self) -> usize {
+        1 << self.addr_width
+    }
+
+    // a ROM is picked either by its label or its location
+    pub fn matches(&self, name: &str) -> bool {
+        self.label.as_deref() == Some(name) || self.loc == name || self.loc == format!("({})", name)
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.label {
+            Some(label) => format!("ROM '{}' at {}", label, self.loc),
+            None => format!("ROM at {}", self.loc),
+        }
+    }
+}
+
+// the value of a <a name="..." val="..."/> attribute inside a component
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let pattern = format!("<a name=\"{}\" val=\"", name);
+    let start = element.find(&pattern)? + pattern.len();
+    let end = element[start..].find('"')? + start;
+    Some(&element[start..end])
+}
+
+// every ROM component in the file (in every circuit), in the order they appear
+pub fn find_roms(circ: &str) -> Vec<Rom> {
+    let mut roms = vec![];
+    let mut search_from = 0;
+    while let Some(start) = circ[search_from..].find("<comp ").map(|start| start + search_from) {
+        let open_end = match circ[start..].find('>') {
+            Some(end) => start + end + 1,
+            None => break,
+        };
+        let header = &circ[start..open_end];
+        // components without attributes are self-closing
+        let end = if header.ends_with("/>") { open_end }
+            else { circ[open_end..].find("</comp>").map_or(circ.len(), |end| open_end + end + "</comp>".len()) };
+        search_from = end;
+        if !header.contains("name=\"ROM\"") || !header.contains("lib=\"4\"") { continue; }
+
+        let element = &circ[start..end];
+        let loc = header.find("loc=\"")
+            .and_then(|loc_start| header[loc_start + 5..].split('"').next())
+            .unwrap_or_default()
+            .to_string();
+        // logisim leaves out attributes that are still their default (8 bits for both widths)
+        roms.push(Rom {
+            label: attribute(element, "label").map(|label| label.to_string()),
+            loc,
+            addr_width: attribute(element, "addrWidth").and_then(|width| width.parse().ok()).unwrap_or(8),
+            data_width: attribute(element, "dataWidth").and_then(|width| width.parse().ok()).unwrap_or(8),
+            range: start..end,
+        });
+    } roms
+}
+
+// picks the ROMs named, or the one program sized ROM (16 bit address, like the program counter) if none are named
+pub fn select_roms(roms: &[Rom], names: &[String]) -> Result<Vec<Rom>, String> {
+    if names.is_empty() {
+        let program_roms = roms.iter().filter(|rom| rom.addr_width == 16).collect::<Vec<&Rom>>();
+        return match program_roms.as_slice() {
+            [rom] => Ok(vec![(*rom).clone()]),
+            [] => Err(String::from("no ROM with a 16 bit address was found; name the ROM(s) to fill with --rom")),
+            _ => Err(format!("found {} ROMs with a 16 bit address ({}); name the ROM(s) to fill with --rom",
+                program_roms.len(), program_roms.iter().map(|rom| rom.describe()).collect::<Vec<String>>().join(", "))),
+        };
+    }
+    names.iter().map(|name| {
+        roms.iter().find(|rom| rom.matches(name)).cloned()
+            .ok_or_else(|| format!("no ROM labeled or located at '{}' (found: {})", name,
+                roms.iter().map(|rom| rom.describe()).collect::<Vec<String>>().join(", ")))
+    }).collect()
+}
+
+// rewrites the contents of the given ROMs with the program, returning the new file
+// pages fill the ROMs in order, so a ROM holding 4 pages gets pages 0-3 and the next ROM starts at page 4
+pub fn inject_program(circ: &str, program: &Program, roms: &[Rom]) -> Result<String, String> {
+    // working out which pages go where before touching anything
+    let mut assignments = vec![];
+    let mut page = 0;
+    for (index, rom) in roms.iter().enumerate() {
+        if roms[..index].contains(rom) {
+            return Err(format!("{} was given more than once", rom.describe()));
+        }
+        if rom.data_width < 32 {
+            return Err(format!("{} is only {} bits wide, but instructions are 32 bits", rom.describe(), rom.data_width));
+        }
+        if rom.capacity() < PAGE_SIZE {
+            return Err(format!("{} holds {} words, which is less than a page (256 words)", rom.describe(), rom.capacity()));
+        }
+        let page_count = (rom.capacity() / PAGE_SIZE).min(program.pages.len().saturating_sub(page));
+        assignments.push((rom, page..page + page_count));
+        page += page_count;
+    }
+    if page < program.pages.len() {
+        return Err(format!("the program has {} pages, but the ROMs given only hold {}", program.pages.len(), page));
+    }
+
+    // replacing back to front so the earlier ranges stay valid
+    let mut circ = circ.to_string();
+    assignments.sort_by_key(|(rom, _)| std::cmp::Reverse(rom.range.start));
+    for (rom, pages) in assignments {
+        let mut words = vec![];
+        for (index, page) in program.pages[pages].iter().enumerate() {
+            words.resize(index * PAGE_SIZE, 0);
+            words.extend_from_slice(&page.words);
+        }
+        let contents = format!("<a name=\"contents\">addr/data: {} {}\n{}</a>", rom.addr_width, rom.data_width, logisim_words(&words));
+        let element = &circ[rom.range.clone()];
+        let new_element = match element.find("<a name=\"contents\">") {
+            Some(start) => {
+                let end = element[start..].find("</a>").map_or(element.len(), |end| start + end + "</a>".len());
+                format!("{}{}{}", &element[..start], contents, &element[end..])
+            },
+            // the ROM is still empty, so it doesn't have a contents attribute yet
+            None => match element.strip_suffix("/>") {
+                Some(open) => format!("{}>\n      {}\n    </comp>", open.trim_end(), contents),
+                None => {
+                    let close = element.rfind("</comp>").unwrap_or(element.len());
+                    format!("{}  {}\n    {}", element[..close].trim_end_matches(' '), contents, &element[close..])
+                },
+            },
+        };
+        circ.replace_range(rom.range.clone(), &new_element);
+    }
+    Ok(circ)
+}
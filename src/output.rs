@@ -0,0 +1,304 @@
+use crate::assembler::Program;
+use crate::isa::PAGE_SIZE;
+use crate::schematic::{write_schematic, SchematicLayout};
+
+// the different files an assembled program can be written as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Words,     // one hex word per line with a comment naming each page (what disasm reads)
+    BinaryLe,  // raw little endian words, every page padded to 256 words
+    BinaryBe,  // raw big endian words, every page padded to 256 words
+    IntelHex,  // byte addressed intel hex (big endian words), with the records of each page starting at page * 1024
+    Logisim,   // a "v2.0 raw" image for a Logisim ROM's "Load Image"
+    Schematic, // a Sponge .schem of the ROM's bits for WorldEdit, with a bank for each page
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "words" => Some(Format::Words),
+            "bin" | "bin-le" => Some(Format::BinaryLe),
+            "bin-be" => Some(Format::BinaryBe),
+            "ihex" => Some(Format::IntelHex),
+            "logisim" => Some(Format::Logisim),
+            "schem" => Some(Format::Schematic),
+            _ => None,
+        }
+    }
+
+    // whether the format is loaded into the circuit, rather than read back by disasm or a person
+    pub fn is_hardware(self) -> bool {
+        self != Format::Words
+    }
+
+    // guesses the format from an output file's extension
+    pub fn from_path(path: &str) -> Format {
+        match std::path::Path::new(path).extension().and_then(|ext| ext.to_str()) {
+            Some("bin") => Format::BinaryLe,
+            Some("hex" | "ihex") => Format::IntelHex,
+            Some("img") => Format::Logisim,
+            Some("schem") => Format::Schematic,
+            _ => Format::Words,
+        }
+    }
+}
+
+// the bytes of the file for the given format (schematics use the default layout)
+pub fn write_program(program: &Program, format: Format) -> Vec<u8> {
+    match format {
+        Format::Words => words_text(program).into_bytes(),
+        Format::BinaryLe => binary(program, false),
+        Format::BinaryBe => binary(program, true),
+        Format::IntelHex => intel_hex(program).into_bytes(),
+        Format::Logisim => logisim_image(program).into_bytes(),
+        Format::Schematic => write_schematic(program, &SchematicLayout::default()),
+    }
+}
+
+// every word at its address in the program counter's address space (page * 256 + line)
+// the gaps at the end of each page (besides the last) are filled with 0 (Nop)
+pub fn padded_words(program: &Program) -> Vec<u32> {
+    let mut words = vec![];
+    for (index, page) in program.pages.iter().enumerate() {
+        words.resize(index * PAGE_SIZE, 0);
+        words.extend_from_slice(&page.words);
+    } words
+}
+
+// one word per line, with a comment naming each page
+pub fn words_text(program: &Program) -> String {
+    let mut text = String::new();
+    for (index, page) in program.pages.iter().enumerate() {
+        text.push_str(&format!("; page {} ({})\n", index, page.name));
+        for byte in &page.words {
+            text.push_str(&format!("{:08x}\n", byte));
+        }
+    } text
+}
+
+pub fn binary(program: &Program, big_endian: bool) -> Vec<u8> {
+    padded_words(program).into_iter()
+        .flat_map(|word| if big_endian { word.to_be_bytes() } else { word.to_le_bytes() })
+        .collect()
+}
+
+pub fn intel_hex(program: &Program) -> String {
+    let mut text = String::new();
+    let mut upper_address = 0;
+    for (index, page) in program.pages.iter().enumerate() {
+        let bytes = page.words.iter().flat_map(|word| word.to_be_bytes()).collect::<Vec<u8>>();
+        // 16 bytes (4 words) per record
+        for (chunk_index, chunk) in bytes.chunks(16).enumerate() {
+            let address = index * PAGE_SIZE * 4 + chunk_index * 16;
+            if address >> 16 != upper_address {
+                // the extended linear address record for anything past 64k
+                upper_address = address >> 16;
+                text.push_str(&intel_hex_record(0, 0x04, &(upper_address as u16).to_be_bytes()));
+            }
+            text.push_str(&intel_hex_record(address as u16, 0x00, chunk));
+        }
+    }
+    text.push_str(&intel_hex_record(0, 0x01, &[]));
+    text
+}
+
+fn intel_hex_record(address: u16, record_type: u8, data: &[u8]) -> String {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&address.to_be_bytes());
+    bytes.push(record_type);
+    bytes.extend_from_slice(data);
+    let checksum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+    bytes.push(checksum);
+    format!(":{}\n", bytes.iter().map(|byte| format!("{:02X}", byte)).collect::<String>())
+}
+
+pub fn logisim_image(program: &Program) -> String {
+    format!("v2.0 raw\n{}", logisim_words(&padded_words(program)))
+}
+
+// the value list used by Logisim's images and .circ memory contents
+// 8 values per line, with runs of the same value written as count*value
+pub fn logisim_words(words: &[u32]) -> String {
+    let mut values = vec![];
+    let mut index = 0;
+    while index < words.len() {
+        let run = words[index..].iter().take_while(|word| **word == words[index]).count();
+        if run >= 4 {
+            values.push(format!("{}*{:x}", run, words[index]));
+            index += run;
+        } else {
+            values.push(format!("{:x}", words[index]));
+            index += 1;
+        }
+    }
+    values.chunks(8).map(|line| format!("{}\n", line.join(" "))).collect()
+}
+
+// a ram or disc image, as a "v2.0 raw" image that a Logisim RAM can load too
+pub fn memory_image(bytes: &[u8]) -> String {
+    format!("v2.0 raw\n{}", logisim_words(&bytes.iter().map(|byte| *byte as u32).collect::<Vec<u32>>()))
+}
+
+// reads a ram or disc image, either written by memory_image (or Logisim) or as raw bytes, padding it out to 256 bytes
+pub fn read_memory_image(bytes: &[u8]) -> Result<[u8; 256], String> {
+    let values = match detect_format(bytes, "") {
+        Format::Logisim => read_logisim_image(std::str::from_utf8(bytes).map_err(|_| String::from("the file isn't valid text"))?)?
+            .into_iter()
+            .map(|value| u8::try_from(value).map_err(|_| format!("{:x} doesn't fit in a byte", value)))
+            .collect::<Result<Vec<u8>, String>>()?,
+        _ => bytes.to_vec(),
+    };
+    if values.len() > 256 { return Err(format!("the image has {} bytes, but ram and disc only hold 256", values.len())); }
+    let mut image = [0u8; 256];
+    image[..values.len()].copy_from_slice(&values);
+    Ok(image)
+}
+
+// guesses the format of a file written by assemble (or dumped from Logisim) from its contents, then its extension
+pub fn detect_format(bytes: &[u8], path: &str) -> Format {
+    let text = String::from_utf8_lossy(&bytes[..bytes.len().min(64)]);
+    let text = text.trim_start();
+    if text.starts_with("v2.0 raw") || text.starts_with("addr/data:") { return Format::Logisim; }
+    if text.starts_with(':') { return Format::IntelHex; }
+    if text.starts_with("; page") { return Format::Words; }
+    Format::from_path(path)
+}
+
+// reads a program back into its pages of words
+pub fn read_program(bytes: &[u8], format: Format) -> Result<Vec<Vec<u32>>, String> {
+    let text = || std::str::from_utf8(bytes).map_err(|_| String::from("the file isn't valid text"));
+    match format {
+        Format::Words => read_words_text(text()?),
+        Format::BinaryLe | Format::BinaryBe => {
+            if !bytes.len().is_multiple_of(4) {
+                return Err(format!("the file is {} bytes, which isn't a whole number of 32 bit words", bytes.len()));
+            }
+            let words = bytes.chunks(4)
+                .map(|chunk| {
+                    let chunk = [chunk[0], chunk[1], chunk[2], chunk[3]];
+                    if format == Format::BinaryBe { u32::from_be_bytes(chunk) } else { u32::from_le_bytes(chunk) }
+                }).collect::<Vec<u32>>();
+            Ok(split_pages(&words))
+        },
+        Format::IntelHex => read_intel_hex(text()?).map(|words| split_pages(&words)),
+        Format::Logisim => read_logisim_image(text()?).map(|words| split_pages(&words)),
+        Format::Schematic => Err(String::from("schematics can't be read back")),
+    }
+}
+
+// undoes padded_words, dropping the padding at the end of each page (everything but the last)
+fn split_pages(words: &[u32]) -> Vec<Vec<u32>> {
+    let mut pages = words.chunks(PAGE_SIZE).map(|page| page.to_vec()).collect::<Vec<Vec<u32>>>();
+    let page_count = pages.len();
+    for page in pages.iter_mut().take(page_count.saturating_sub(1)) {
+        while page.last() == Some(&0) { page.pop(); }
+    }
+    // dumps of a whole ROM end in pages that were never written to
+    while pages.len() > 1 && pages.last().is_some_and(|page| page.iter().all(|word| *word == 0)) { pages.pop(); }
+    if pages.is_empty() { pages.push(vec![]); }
+    pages
+}
+
+fn read_words_text(text: &str) -> Result<Vec<Vec<u32>>, String> {
+    let mut pages: Vec<Vec<u32>> = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        // every page starts with a comment
+        if line.starts_with(';') { pages.push(vec![]); continue; }
+        let word = u32::from_str_radix(line, 16).map_err(|_| format!("line {}: '{}' isn't a hex word", index + 1, line))?;
+        match pages.last_mut() {
+            Some(page) => page.push(word),
+            None => pages.push(vec![word]),
+        }
+    }
+    if pages.is_empty() { pages.push(vec![]); }
+    Ok(pages)
+}
+
+fn read_intel_hex(text: &str) -> Result<Vec<u32>, String> {
+    let mut bytes = vec![];
+    let mut upper_address = 0;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() { continue; }
+        let error = || format!("line {}: '{}' isn't an intel hex record", index + 1, line);
+        let record = line.strip_prefix(':')
+            .filter(|record| record.len().is_multiple_of(2) && record.len() >= 10)
+            .and_then(|record| (0..record.len()).step_by(2).map(|i| u8::from_str_radix(&record[i..i + 2], 16).ok()).collect::<Option<Vec<u8>>>())
+            .ok_or_else(error)?;
+        if record.len() != record[0] as usize + 5 { return Err(error()); }
+        if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(format!("line {}: the checksum doesn't match", index + 1));
+        }
+        let address = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..record.len() - 1];
+        match record[3] {
+            0x00 => {
+                let start = upper_address + address;
+                if bytes.len() < start + data.len() { bytes.resize(start + data.len(), 0); }
+                bytes[start..start + data.len()].copy_from_slice(data);
+            },
+            0x01 => break,
+            0x04 if data.len() == 2 => { upper_address = (u16::from_be_bytes([data[0], data[1]]) as usize) << 16; },
+            _ => return Err(format!("line {}: unsupported record type {:02x}", index + 1, record[3])),
+        }
+    }
+    bytes.resize(bytes.len().div_ceil(4) * 4, 0);
+    Ok(bytes.chunks(4).map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])).collect())
+}
+
+// reads both "v2.0 raw" images and the "addr/data: 16 32" contents from a .circ
+fn read_logisim_image(text: &str) -> Result<Vec<u32>, String> {
+    let mut words = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("v2.0 raw") || line.starts_with("addr/data:") { continue; }
+        for value in line.split_whitespace() {
+            let error = || format!("line {}: '{}' isn't a hex value", index + 1, value);
+            let (count, value) = match value.split_once('*') {
+                Some((count, value)) => (count.parse::<usize>().map_err(|_| error())?, value),
+                None => (1, value),
+            };
+            let value = u32::from_str_radix(value, 16).map_err(|_| error())?;
+            words.extend(std::iter::repeat_n(value, count));
+        }
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembler::Page;
+
+    fn program(pages: Vec<Vec<u32>>) -> Program {
+        Program { pages: pages.into_iter().map(|words| Page { words, ..Page::default() }).collect(), ..Program::default() }
+    }
+
+    #[test]
+    fn intel_hex_round_trip() {
+        // 70 pages go past 64k, which needs the extended address records (and no page ends in a Nop, which would read back as padding)
+        let pages = (0..70u32).map(|page| (0..page % 7 + 1).map(|line| page << 24 | (line + 1)).collect()).collect::<Vec<Vec<u32>>>();
+        let text = intel_hex(&program(pages.clone()));
+        assert!(text.contains(":020000040001F9\n"));
+        assert!(text.ends_with(":00000001FF\n"));
+        assert_eq!(read_program(text.as_bytes(), Format::IntelHex), Ok(pages));
+    }
+
+    #[test]
+    fn intel_hex_errors() {
+        assert_eq!(read_intel_hex(":0400000005000000F8\n"), Err(String::from("line 1: the checksum doesn't match")));
+        assert_eq!(read_intel_hex("05000000\n"), Err(String::from("line 1: '05000000' isn't an intel hex record")));
+        assert_eq!(read_intel_hex(":00000002FE\n"), Err(String::from("line 1: unsupported record type 02")));
+    }
+
+    #[test]
+    fn other_formats_round_trip() {
+        let pages = vec![vec![0x05000000, 0x66000100], vec![], vec![0xc0010000; 300 - 256]];
+        let program = program(pages.clone());
+        for format in [Format::Words, Format::BinaryLe, Format::BinaryBe, Format::Logisim] {
+            assert_eq!(read_program(&write_program(&program, format), format), Ok(pages.clone()), "{:?}", format);
+        }
+    }
+}
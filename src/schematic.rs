@@ -0,0 +1,170 @@
+use crate::assembler::Program;
+
+// the minecraft version the schematic claims to be from (1.20.4), which WorldEdit uses to upgrade the blocks if needed
+const DATA_VERSION: i32 = 3700;
+
+pub const MAX_SPACING: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis { X, Y, Z }
+
+// how the ROM's bits are laid out in the world
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchematicLayout {
+    pub one_block: String,   // the block for a set bit
+    pub zero_block: String,  // the block for a clear bit (can be minecraft:air)
+    // which direction the bits of a word, the words of a page, and the pages (banks) run
+    pub bit_axis: Axis,
+    pub word_axis: Axis,
+    pub page_axis: Axis,
+    // the distance from one bit/word/page to the next (1 is packed right next to each other)
+    // kept to MAX_SPACING or less so the schematic's size always fits in its 16 bit dimensions
+    pub bit_spacing: usize,
+    pub word_spacing: usize,
+    pub page_spacing: usize,
+}
+
+impl Default for SchematicLayout {
+    // bits stacked upwards (bit 0 at the bottom), words along x, and a bank for each page along z
+    fn default() -> SchematicLayout {
+        SchematicLayout {
+            one_block: String::from("minecraft:redstone_block"),
+            zero_block: String::from("minecraft:glass"),
+            bit_axis: Axis::Y,
+            word_axis: Axis::X,
+            page_axis: Axis::Z,
+            bit_spacing: 2,
+            word_spacing: 2,
+            page_spacing: 4,
+        }
+    }
+}
+
+impl SchematicLayout {
+    // sets the axes from something like "yxz" (bits, then words, then pages)
+    pub fn set_axes(&mut self, axes: &str) -> Result<(), String> {
+        let parsed = axes.chars().map(|c| match c.to_ascii_lowercase() {
+            'x' => Ok(Axis::X),
+            'y' => Ok(Axis::Y),
+            'z' => Ok(Axis::Z),
+            other => Err(format!("'{}' isn't an axis (expected x, y or z)", other)),
+        }).collect::<Result<Vec<Axis>, String>>()?;
+        match parsed.as_slice() {
+            [bits, words, pages] if bits != words && bits != pages && words != pages => {
+                self.bit_axis = *bits;
+                self.word_axis = *words;
+                self.page_axis = *pages;
+                Ok(())
+            },
+            _ => Err(format!("the layout '{}' needs each of x, y and z once (bits, then words, then pages)", axes)),
+        }
+    }
+}
+
+// the gzipped Sponge schematic (v2) for the program, readable by WorldEdit and most other tools
+pub fn write_schematic(program: &Program, layout: &SchematicLayout) -> Vec<u8> {
+    // air is always 0, since that's what the gaps between bits are filled with
+    let mut palette = vec![String::from("minecraft:air")];
+    for block in [&layout.one_block, &layout.zero_block] {
+        if !palette.contains(block) { palette.push(block.clone()); }
+    }
+    let one_index = palette.iter().position(|block| *block == layout.one_block).unwrap() as u8;
+    let zero_index = palette.iter().position(|block| *block == layout.zero_block).unwrap() as u8;
+
+    // every block, as ([x, y, z], palette index)
+    let mut blocks = vec![];
+    for (page_index, page) in program.pages.iter().enumerate() {
+        for (word_index, word) in page.words.iter().enumerate() {
+            for bit in 0..32 {
+                let mut position = [0usize; 3];
+                position[axis_index(layout.bit_axis)] += bit * layout.bit_spacing;
+                position[axis_index(layout.word_axis)] += word_index * layout.word_spacing;
+                position[axis_index(layout.page_axis)] += page_index * layout.page_spacing;
+                let palette_index = if (word >> bit) & 1 == 1 { one_index } else { zero_index };
+                blocks.push((position, palette_index));
+            }
+        }
+    }
+
+    let mut size = [1usize; 3];
+    for (position, _) in &blocks {
+        for axis in 0..3 { size[axis] = size[axis].max(position[axis] + 1); }
+    }
+    let [width, height, length] = size;
+    // the palette indices are var-ints, which for 3 or less blocks is always a single byte each
+    let mut block_data = vec![0u8; width * height * length];
+    for ([x, y, z], palette_index) in blocks {
+        block_data[x + z * width + y * width * length] = palette_index;
+    }
+
+    let mut nbt = vec![];
+    nbt_tag(&mut nbt, 10, "Schematic");
+    nbt_tag(&mut nbt, 3, "Version");
+    nbt.extend_from_slice(&2i32.to_be_bytes());
+    nbt_tag(&mut nbt, 3, "DataVersion");
+    nbt.extend_from_slice(&DATA_VERSION.to_be_bytes());
+    nbt_tag(&mut nbt, 2, "Width");
+    nbt.extend_from_slice(&(width as u16).to_be_bytes());
+    nbt_tag(&mut nbt, 2, "Height");
+    nbt.extend_from_slice(&(height as u16).to_be_bytes());
+    nbt_tag(&mut nbt, 2, "Length");
+    nbt.extend_from_slice(&(length as u16).to_be_bytes());
+    nbt_tag(&mut nbt, 11, "Offset");
+    nbt.extend_from_slice(&3i32.to_be_bytes());
+    nbt.extend_from_slice(&[0u8; 12]);
+    nbt_tag(&mut nbt, 3, "PaletteMax");
+    nbt.extend_from_slice(&(palette.len() as i32).to_be_bytes());
+    nbt_tag(&mut nbt, 10, "Palette");
+    for (index, block) in palette.iter().enumerate() {
+        nbt_tag(&mut nbt, 3, block);
+        nbt.extend_from_slice(&(index as i32).to_be_bytes());
+    }
+    nbt.push(0);  // end of the palette
+    nbt_tag(&mut nbt, 7, "BlockData");
+    nbt.extend_from_slice(&(block_data.len() as i32).to_be_bytes());
+    nbt.extend_from_slice(&block_data);
+    nbt_tag(&mut nbt, 9, "BlockEntities");
+    nbt.push(10);
+    nbt.extend_from_slice(&0i32.to_be_bytes());
+    nbt.push(0);  // end of the schematic
+
+    gzip_stored(&nbt)
+}
+
+fn axis_index(axis: Axis) -> usize {
+    match axis { Axis::X => 0, Axis::Y => 1, Axis::Z => 2 }
+}
+
+// the id and name that start every named tag
+fn nbt_tag(nbt: &mut Vec<u8>, id: u8, name: &str) {
+    nbt.push(id);
+    nbt.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    nbt.extend_from_slice(name.as_bytes());
+}
+
+// a gzip file using uncompressed (stored) deflate blocks, which every reader accepts
+// the schematics are small enough that actually compressing them isn't worth pulling in a crate
+fn gzip_stored(data: &[u8]) -> Vec<u8> {
+    let mut gzip = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    let mut chunks = data.chunks(0xFFFF).peekable();
+    if chunks.peek().is_none() { gzip.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]); }
+    while let Some(chunk) = chunks.next() {
+        gzip.push(if chunks.peek().is_none() { 1 } else { 0 });
+        gzip.extend_from_slice(&(chunk.len() as u16).to_le_bytes());
+        gzip.extend_from_slice(&(!(chunk.len() as u16)).to_le_bytes());
+        gzip.extend_from_slice(chunk);
+    }
+    gzip.extend_from_slice(&crc32(data).to_le_bytes());
+    gzip.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    gzip
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    } !crc
+}
@@ -0,0 +1,102 @@
+use crate::assembler::{Header, Program};
+use crate::isa::PAGE_SIZE;
+
+// the names behind a program's numbers, so tools can show `FibLoop` instead of 3
+// the text form is one symbol per line (blank lines and ; comments are skipped):
+//     page <index> <name>
+//     header <page> <line> <name>
+//     macro <name>
+// there's also a JSON form for other tools, which is only ever written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolTable {
+    pub pages: Vec<String>,
+    pub headers: Vec<Header>,  // name, line, page
+    pub macros: Vec<String>,   // the exported macros
+}
+
+impl SymbolTable {
+    pub fn from_program(program: &Program) -> SymbolTable {
+        SymbolTable {
+            pages: program.pages.iter().map(|page| page.name.clone()).collect(),
+            headers: program.headers.clone(),
+            macros: program.macros.clone(),
+        }
+    }
+
+    pub fn parse_text(text: &str) -> Result<SymbolTable, String> {
+        let mut symbols = SymbolTable::default();
+        for (index, line) in text.lines().enumerate() {
+            let tokens = line.split_whitespace().collect::<Vec<&str>>();
+            let number = |token: &str| token.parse::<usize>().map_err(|_| format!("line {}: expected a number, found '{}'", index + 1, token));
+            match tokens.as_slice() {
+                [] => {},
+                [first, ..] if first.starts_with(';') => {},
+                ["page", page, name] => {
+                    let page = number(page)?;
+                    if symbols.pages.len() <= page { symbols.pages.resize(page + 1, String::new()); }
+                    symbols.pages[page] = name.to_string();
+                },
+                ["header", page, line, name] => symbols.headers.push((name.to_string(), number(line)?, number(page)?)),
+                ["macro", name] => symbols.macros.push(name.to_string()),
+                [first, ..] if index == 0 && first.starts_with('{') => return Err(String::from("only the text form of a symbol file can be read, not the JSON one")),
+                _ => return Err(format!("line {}: expected 'page <index> <name>', 'header <page> <line> <name>' or 'macro <name>'", index + 1)),
+            }
+        }
+        Ok(symbols)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("; page <index> <name>, header <page> <line> <name>, macro <name>\n");
+        for (index, name) in self.pages.iter().enumerate() {
+            text.push_str(&format!("page {} {}\n", index, name));
+        }
+        for (name, line, page) in &self.headers {
+            text.push_str(&format!("header {} {} {}\n", page, line, name));
+        }
+        for name in &self.macros {
+            text.push_str(&format!("macro {}\n", name));
+        } text
+    }
+
+    // headers also get their full address (the program counter when the jump lands, page * 256 + line)
+    pub fn to_json(&self) -> String {
+        let pages = self.pages.iter().enumerate()
+            .map(|(index, name)| format!("    {{ \"index\": {}, \"name\": {} }}", index, json_string(name)))
+            .collect::<Vec<String>>();
+        let headers = self.headers.iter()
+            .map(|(name, line, page)| format!("    {{ \"name\": {}, \"page\": {}, \"line\": {}, \"address\": {} }}",
+                json_string(name), page, line, page * PAGE_SIZE + line))
+            .collect::<Vec<String>>();
+        let macros = self.macros.iter().map(|name| format!("    {}", json_string(name))).collect::<Vec<String>>();
+        format!("{{\n  \"pages\": {},\n  \"headers\": {},\n  \"macros\": {}\n}}\n", json_array(&pages), json_array(&headers), json_array(&macros))
+    }
+
+    // the name of a page, if it has one
+    pub fn page_name(&self, page: usize) -> Option<&str> {
+        self.pages.get(page).map(|name| name.as_str()).filter(|name| !name.is_empty())
+    }
+
+    // every header at the given spot
+    pub fn headers_at(&self, page: usize, line: usize) -> impl Iterator<Item = &str> {
+        self.headers.iter().filter(move |h| h.2 == page && h.1 == line).map(|h| h.0.as_str())
+    }
+}
+
+fn json_array(items: &[String]) -> String {
+    if items.is_empty() { return String::from("[]"); }
+    format!("[\n{}\n  ]", items.join(",\n"))
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
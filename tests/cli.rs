@@ -0,0 +1,30 @@
+// runs the built binary the way it's used from a terminal
+use std::process::{Command, Output};
+
+// writes the script to a file of its own in the temp directory and runs the binary on it with the arguments
+fn run(name: &str, script: &str, args: &[&str]) -> Output {
+    let path = std::env::temp_dir().join(format!("McAssembler-{}-{}.mca", name, std::process::id()));
+    std::fs::write(&path, script).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_McAssembler")).args(args).arg(&path).arg("-q").output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    output
+}
+
+static READS_ROM: &str = "SetPtr text\nRomL main\nKill\n!header text\n!string \"hi\"\n";
+
+#[test]
+fn hardware_formats_refuse_emulator_only_instructions() {
+    for format in ["logisim", "bin", "bin-be", "ihex", "schem"] {
+        let output = run(&format!("rom-{}", format), READS_ROM, &["assemble", "-f", format]);
+        assert!(!output.status.success(), "-f {} wrote a program using RomL", format);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("doesn't decode RomL/RomR") && stderr.contains("RomL at "), "-f {}: {}", format, stderr);
+        assert!(output.stdout.is_empty(), "-f {} still wrote output", format);
+    }
+}
+
+#[test]
+fn words_and_check_allow_emulator_only_instructions() {
+    assert!(run("rom-words", READS_ROM, &["assemble", "-f", "words"]).status.success());
+    assert!(run("rom-check", READS_ROM, &["check"]).status.success());
+}
@@ -0,0 +1,23 @@
+// every script shipped in scripts/ has to keep assembling (the libraries on their own too)
+use McAssembler::{assemble_with, AssembleOptions};
+
+fn scripts(dir: &std::path::Path, found: &mut Vec<std::path::PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() { scripts(&path, found); }
+        else if path.extension().is_some_and(|ext| ext == "mca") { found.push(path); }
+    }
+}
+
+#[test]
+fn every_script_assembles() {
+    let mut found = vec![];
+    scripts(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("scripts"), &mut found);
+    assert!(found.iter().any(|path| path.ends_with("pong.mca")));
+    for path in found {
+        let name = path.to_str().unwrap();
+        if let Err(errors) = assemble_with(name, &std::fs::read_to_string(&path).unwrap(), &AssembleOptions::default()) {
+            panic!("{} doesn't assemble:\n{}", name, McAssembler::assembler::render_errors(&errors));
+        }
+    }
+}